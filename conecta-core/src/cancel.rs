@@ -0,0 +1,221 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Why a load was stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CancelReason {
+    /// `CancelToken::cancel` was called, e.g. by Ctrl-C in Python.
+    Cancelled,
+
+    /// The load took longer than the timeout of the `CancelToken`.
+    TimedOut,
+}
+
+const RUNNING: u8 = 0;
+const CANCELLED: u8 = 1;
+const TIMED_OUT: u8 = 2;
+
+type OnCancel = Box<dyn Fn() + Send>;
+
+/// Cancels a load from another thread, or once it takes longer than a timeout.
+///
+/// Workers call `check` between rows, which panics if the load was cancelled, and register the
+/// running queries with `on_cancel` so they are cancelled in the database as well, e.g. with
+/// Postgres' `CancelToken`, otherwise a worker waiting for the first row of a slow query would
+/// never get to `check`.
+///
+/// Clones share the same state, so a clone can be cancelled from another thread.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    state: Arc<AtomicU8>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+
+    /// The functions that cancel the running queries, by id.
+    on_cancel: Arc<Mutex<Vec<(usize, OnCancel)>>>,
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("reason", &self.reason())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl CancelToken {
+    /// Creates a token that times out `timeout` after now, `None` never times out.
+    pub fn new(timeout: Option<Duration>) -> Self {
+        CancelToken {
+            timeout,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            ..CancelToken::default()
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel_with(CancelReason::Cancelled);
+    }
+
+    fn cancel_with(&self, reason: CancelReason) {
+        let state = match reason {
+            CancelReason::Cancelled => CANCELLED,
+            CancelReason::TimedOut => TIMED_OUT,
+        };
+        if self
+            .state
+            .compare_exchange(RUNNING, state, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            for (_, cancel) in self.on_cancel.lock().unwrap().iter() {
+                cancel();
+            }
+        }
+    }
+
+    /// Returns why the load was stopped, or `None` if it is still running.
+    pub fn reason(&self) -> Option<CancelReason> {
+        match self.state.load(Ordering::SeqCst) {
+            CANCELLED => Some(CancelReason::Cancelled),
+            TIMED_OUT => Some(CancelReason::TimedOut),
+            _ => None,
+        }
+    }
+
+    /// Panics if the load was cancelled or timed out.
+    pub fn check(&self) {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.cancel_with(CancelReason::TimedOut);
+        }
+        match self.reason() {
+            Some(CancelReason::Cancelled) => panic!("The load was cancelled"),
+            Some(CancelReason::TimedOut) => {
+                panic!("The load timed out after {:?}", self.timeout.unwrap())
+            }
+            None => {}
+        }
+    }
+
+    /// Registers `cancel` to be called if the load is cancelled while the returned guard is
+    /// alive, it is called right away if it already was.
+    pub fn on_cancel(&self, cancel: impl Fn() + Send + 'static) -> OnCancelGuard<'_> {
+        let mut on_cancel = self.on_cancel.lock().unwrap();
+        if self.reason().is_some() {
+            cancel();
+        }
+        let id = on_cancel.last().map_or(0, |(id, _)| id + 1);
+        on_cancel.push((id, Box::new(cancel)));
+        OnCancelGuard { token: self, id }
+    }
+
+    /// Runs `load`, cancelling it with `CancelReason::TimedOut` from another thread once the
+    /// timeout is reached.
+    pub fn watch<R>(&self, load: impl FnOnce() -> R) -> R {
+        let Some(deadline) = self.deadline else {
+            return load();
+        };
+
+        let (done, wait) = mpsc::channel::<()>();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                if let Err(RecvTimeoutError::Timeout) = wait.recv_timeout(timeout) {
+                    self.cancel_with(CancelReason::TimedOut);
+                }
+            });
+            let result = load();
+            drop(done);
+            result
+        })
+    }
+}
+
+/// Unregisters a function given to `CancelToken::on_cancel` when dropped.
+pub struct OnCancelGuard<'a> {
+    token: &'a CancelToken,
+    id: usize,
+}
+
+impl Drop for OnCancelGuard<'_> {
+    fn drop(&mut self) {
+        self.token
+            .on_cancel
+            .lock()
+            .unwrap()
+            .retain(|(id, _)| *id != self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_cancel() {
+        let token = CancelToken::default();
+        token.check();
+        assert_eq!(token.reason(), None);
+
+        token.clone().cancel();
+        assert_eq!(token.reason(), Some(CancelReason::Cancelled));
+        let panic = std::panic::catch_unwind(|| token.check()).unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<&str>(),
+            Some(&"The load was cancelled")
+        );
+    }
+
+    #[test]
+    fn test_on_cancel() {
+        let token = CancelToken::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let counter = calls.clone();
+        let guard = token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let counter = calls.clone();
+        drop(token.on_cancel(move || {
+            counter.fetch_add(10, Ordering::SeqCst);
+        }));
+
+        token.cancel();
+        token.cancel();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        drop(guard);
+    }
+
+    #[test]
+    fn test_watch_times_out() {
+        let token = CancelToken::new(Some(Duration::from_millis(10)));
+        let cancelled = Arc::new(AtomicUsize::new(0));
+
+        let counter = cancelled.clone();
+        token.watch(|| {
+            let _guard = token.on_cancel(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+            // A query that is only stopped by its cancellation.
+            while cancelled.load(Ordering::SeqCst) == 0 {
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        assert_eq!(token.reason(), Some(CancelReason::TimedOut));
+    }
+
+    #[test]
+    fn test_watch_finishes() {
+        let token = CancelToken::new(Some(Duration::from_secs(60)));
+        assert_eq!(token.watch(|| 1), 1);
+        assert_eq!(token.reason(), None);
+    }
+}
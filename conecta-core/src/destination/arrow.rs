@@ -1,18 +1,84 @@
-use crate::schema::{NativeType, Schema};
+use crate::schema::{
+    geoarrow_box_fields, geoarrow_vertices_field, geoarrow_xy_fields, sparse_vector_fields, Column,
+    NativeType, Schema,
+};
 use arrow::array::{
-    make_builder, ArrayBuilder, BinaryBuilder, BooleanBuilder, FixedSizeBinaryBuilder,
-    Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder, ListBuilder,
-    StringBuilder,
+    make_builder, Array, ArrayBuilder, ArrayRef, AsArray, BinaryBuilder, BooleanBuilder,
+    FixedSizeBinaryBuilder, FixedSizeListBuilder, Float16Builder, Float32Builder, Float64Builder,
+    Int16Builder, Int32Builder, Int64Builder, LargeStringBuilder, ListBuilder, StringBuilder,
+    StringDictionaryBuilder, StringViewBuilder, StructBuilder,
 };
+use arrow::datatypes::{DataType, Field, Int32Type};
+use std::sync::Arc;
+
+/// Returns a builder of GeoArrow coordinates, a struct of `x` and `y`.
+fn geoarrow_xy_builder(capacity: usize) -> StructBuilder {
+    StructBuilder::new(
+        geoarrow_xy_fields(),
+        vec![
+            Box::new(Float64Builder::with_capacity(capacity)),
+            Box::new(Float64Builder::with_capacity(capacity)),
+        ],
+    )
+}
+
+/// Size in bytes of the data buffer of variable-length builders when the average width of
+/// the column is not known.
+const DEFAULT_DATA_CAPACITY: usize = 1024;
+
+/// Returns the bytes to preallocate for the values of `column` given `capacity` items, from
+/// `Column::avg_width`. `None` if the builder of the column has no variable-length data buffer.
+pub fn data_capacity(column: &Column, capacity: usize) -> Option<usize> {
+    match column.data_type {
+        NativeType::String
+        | NativeType::LargeString
+        | NativeType::Bytes
+        | NativeType::PgGis
+        | NativeType::GeoArrowWkb(_) => Some(match column.avg_width {
+            Some(avg_width) if capacity > 0 => {
+                avg_width.saturating_mul(capacity).min(i32::MAX as usize)
+            }
+            _ => DEFAULT_DATA_CAPACITY,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the length in bytes of the values of a variable-length `array`, 0 for other types.
+pub fn data_len(array: &ArrayRef) -> usize {
+    match array.data_type() {
+        DataType::Utf8 => array.as_string::<i32>().values().len(),
+        DataType::LargeUtf8 => array.as_string::<i64>().values().len(),
+        DataType::Binary => array.as_binary::<i32>().values().len(),
+        _ => 0,
+    }
+}
 
 pub fn get_arrow_builders(schema: &Schema, capacity: usize) -> Vec<Box<dyn ArrayBuilder>> {
     let mut builders: Vec<Box<dyn ArrayBuilder>> = Vec::with_capacity(schema.columns.len());
     for column in schema.columns.iter() {
+        let data_capacity = || data_capacity(column, capacity).unwrap();
         match column.data_type {
-            NativeType::String => {
-                builders.push(Box::new(StringBuilder::with_capacity(capacity, 1024)))
+            NativeType::String => builders.push(Box::new(StringBuilder::with_capacity(
+                capacity,
+                data_capacity(),
+            ))),
+            NativeType::LargeString => builders.push(Box::new(LargeStringBuilder::with_capacity(
+                capacity,
+                data_capacity(),
+            ))),
+            NativeType::StringView => {
+                builders.push(Box::new(StringViewBuilder::with_capacity(capacity)))
             }
-            NativeType::Bytes => builders.push(Box::new(BinaryBuilder::new())),
+            NativeType::StringDictionary => {
+                builders.push(Box::new(
+                    StringDictionaryBuilder::<Int32Type>::with_capacity(capacity, 256, 1024),
+                ))
+            }
+            NativeType::Bytes => builders.push(Box::new(BinaryBuilder::with_capacity(
+                capacity,
+                data_capacity(),
+            ))),
             NativeType::VecI16 => builders.push(Box::new(ListBuilder::new(Int16Builder::new()))),
             NativeType::VecI32 => builders.push(Box::new(ListBuilder::new(Int32Builder::new()))),
             NativeType::VecI64 => builders.push(Box::new(ListBuilder::new(Int64Builder::new()))),
@@ -48,9 +114,163 @@ pub fn get_arrow_builders(schema: &Schema, capacity: usize) -> Vec<Box<dyn Array
             ))),
             NativeType::Path => builders.push(Box::new(ListBuilder::new(Float64Builder::new()))),
             NativeType::Polygon => builders.push(Box::new(ListBuilder::new(Float64Builder::new()))),
-            NativeType::PgGis => builders.push(Box::new(BinaryBuilder::new())),
+            NativeType::PgGis => builders.push(Box::new(BinaryBuilder::with_capacity(
+                capacity,
+                data_capacity(),
+            ))),
+            NativeType::PgVector(Some(dim)) => {
+                builders.push(Box::new(FixedSizeListBuilder::with_capacity(
+                    Float32Builder::with_capacity(capacity * dim as usize),
+                    dim,
+                    capacity,
+                )))
+            }
+            NativeType::PgVector(None) => {
+                builders.push(Box::new(ListBuilder::new(Float32Builder::new())))
+            }
+            NativeType::PgHalfVector(Some(dim)) => {
+                builders.push(Box::new(FixedSizeListBuilder::with_capacity(
+                    Float16Builder::with_capacity(capacity * dim as usize),
+                    dim,
+                    capacity,
+                )))
+            }
+            NativeType::PgHalfVector(None) => {
+                builders.push(Box::new(ListBuilder::new(Float16Builder::new())))
+            }
+            NativeType::GeoArrowPoint => {
+                builders.push(Box::new(geoarrow_xy_builder(capacity)));
+            }
+            NativeType::GeoArrowLineSegment | NativeType::GeoArrowPath => builders.push(Box::new(
+                ListBuilder::with_capacity(geoarrow_xy_builder(capacity * 2), capacity)
+                    .with_field(geoarrow_vertices_field()),
+            )),
+            NativeType::GeoArrowBox => builders.push(Box::new(StructBuilder::new(
+                geoarrow_box_fields(),
+                (0..4)
+                    .map(|_| {
+                        Box::new(Float64Builder::with_capacity(capacity)) as Box<dyn ArrayBuilder>
+                    })
+                    .collect(),
+            ))),
+            NativeType::GeoArrowPolygon => builders.push(Box::new(
+                ListBuilder::with_capacity(
+                    ListBuilder::new(geoarrow_xy_builder(0)).with_field(geoarrow_vertices_field()),
+                    capacity,
+                )
+                .with_field(Arc::new(Field::new(
+                    "rings",
+                    DataType::List(geoarrow_vertices_field()),
+                    false,
+                ))),
+            )),
+            NativeType::GeoArrowWkb(_) => builders.push(Box::new(BinaryBuilder::with_capacity(
+                capacity,
+                data_capacity(),
+            ))),
+            NativeType::PgSparseVector => builders.push(Box::new(StructBuilder::new(
+                sparse_vector_fields(),
+                vec![
+                    Box::new(Int32Builder::with_capacity(capacity)),
+                    Box::new(ListBuilder::new(Int32Builder::new())),
+                    Box::new(ListBuilder::new(Float32Builder::new())),
+                ],
+            ))),
             _ => builders.push(make_builder(&column.data_type.to_arrow(), capacity)),
         }
     }
     builders
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_of(data_type: NativeType) -> Schema {
+        Schema {
+            columns: vec![Column {
+                name: "col".to_string(),
+                data_type,
+                original_type_repr: "".to_string(),
+                fetch_as_text: false,
+                overridden_from: None,
+                nullable: true,
+                metadata: Default::default(),
+                avg_width: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_builders_match_arrow_type() {
+        for data_type in [
+            NativeType::PgVector(Some(3)),
+            NativeType::PgVector(None),
+            NativeType::PgHalfVector(Some(3)),
+            NativeType::PgHalfVector(None),
+            NativeType::PgSparseVector,
+            NativeType::GeoArrowPoint,
+            NativeType::GeoArrowPath,
+            NativeType::GeoArrowBox,
+            NativeType::GeoArrowPolygon,
+            NativeType::GeoArrowWkb(Some(4326)),
+            NativeType::LargeString,
+            NativeType::StringView,
+            NativeType::StringDictionary,
+        ] {
+            let mut builders = get_arrow_builders(&schema_of(data_type.clone()), 10);
+            let array = builders[0].finish();
+            assert!(array.data_type().equals_datatype(&data_type.to_arrow()));
+        }
+    }
+
+    #[test]
+    fn test_pgsparsevector_builder_null() {
+        let mut builders = get_arrow_builders(&schema_of(NativeType::PgSparseVector), 1);
+        let builder = builders[0]
+            .as_any_mut()
+            .downcast_mut::<StructBuilder>()
+            .unwrap();
+        builder
+            .field_builder::<Int32Builder>(0)
+            .unwrap()
+            .append_null();
+        builder
+            .field_builder::<ListBuilder<Int32Builder>>(1)
+            .unwrap()
+            .append_null();
+        builder
+            .field_builder::<ListBuilder<Float32Builder>>(2)
+            .unwrap()
+            .append_null();
+        builder.append_null();
+
+        let array = builder.finish();
+        assert_eq!(array.null_count(), 1);
+    }
+
+    #[test]
+    fn test_data_capacity() {
+        let mut schema = schema_of(NativeType::String);
+        assert_eq!(data_capacity(&schema.columns[0], 1000), Some(1024));
+
+        schema.columns[0].avg_width = Some(27);
+        assert_eq!(data_capacity(&schema.columns[0], 1000), Some(27_000));
+        // No preallocation.
+        assert_eq!(data_capacity(&schema.columns[0], 0), Some(1024));
+
+        let builder = &mut get_arrow_builders(&schema, 1000)[0];
+        let builder = builder
+            .as_any_mut()
+            .downcast_mut::<StringBuilder>()
+            .unwrap();
+        builder.append_value("a");
+        let array: ArrayRef = Arc::new(builder.finish());
+        assert_eq!(data_len(&array), 1);
+
+        assert_eq!(
+            data_capacity(&schema_of(NativeType::I32).columns[0], 1000),
+            None
+        );
+    }
+}
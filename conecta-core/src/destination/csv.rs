@@ -0,0 +1,76 @@
+use crate::destination::destination::{Destination, WriterState};
+use arrow::csv::{Writer, WriterBuilder};
+use arrow::record_batch::RecordBatch;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Writes the batches as CSV with a header, types without a CSV representation, like lists,
+/// cannot be written.
+pub struct CsvDestination<W: Write + Send> {
+    state: Mutex<WriterState<W, Writer<W>>>,
+}
+
+impl<W: Write + Send> CsvDestination<W> {
+    pub fn new(sink: W) -> Self {
+        CsvDestination {
+            state: Mutex::new(WriterState::Pending(sink)),
+        }
+    }
+}
+
+impl CsvDestination<BufWriter<File>> {
+    /// Creates the file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> Self {
+        let file = File::create(path).expect("Could not create the CSV file");
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write + Send> Destination for CsvDestination<W> {
+    fn write(&self, batch: RecordBatch) -> Result<(), String> {
+        self.state
+            .lock()
+            .unwrap()
+            .writer(|sink| Ok(WriterBuilder::new().with_header(true).build(sink)))?
+            .write(&batch)
+            .map_err(|e| format!("Could not write to the CSV file: {e}"))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if let Some(writer) = self.state.lock().unwrap().take() {
+            writer
+                .into_inner()
+                .flush()
+                .map_err(|e| format!("Could not finish the CSV file: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{ArrayRef, Int32Array, StringArray};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_csv_destination() {
+        let path = std::env::temp_dir().join("conecta_test_csv_destination.csv");
+        let destination = CsvDestination::create(&path);
+        for (ids, names) in [(vec![1, 2], vec!["a", "b"]), (vec![3], vec!["c,d"])] {
+            let ids: ArrayRef = Arc::new(Int32Array::from(ids));
+            let names: ArrayRef = Arc::new(StringArray::from(names));
+            let batch = RecordBatch::try_from_iter([("id", ids), ("name", names)]).unwrap();
+            destination.write(batch).unwrap();
+        }
+        destination.finish().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "id,name\n1,a\n2,b\n3,\"c,d\"\n"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
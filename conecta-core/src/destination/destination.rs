@@ -1,8 +1,52 @@
-use crate::schema::{NativeType, Schema};
-use arrow::array::ArrayBuilder;
+use arrow::record_batch::RecordBatch;
 
-pub trait Destination {
-    fn allocate(&self, type_: NativeType, n: i64) -> Vec<Box<dyn ArrayBuilder>>;
+/// Where the data of a load is written to when it is not kept in memory, e.g. a Parquet file.
+///
+/// Partitions are loaded in parallel, so `write` is called from several threads at once.
+pub trait Destination: Send + Sync {
+    /// Writes a batch of one of the partitions. All batches have the same schema and every
+    /// partition writes at least one, maybe empty, batch.
+    ///
+    /// Errors are returned instead of panicking, so the lock of the writer is not poisoned for
+    /// the partitions that write at the same time.
+    fn write(&self, batch: RecordBatch) -> Result<(), String>;
 
-    fn allocate_schema(&self, schema: Schema) -> Vec<Box<dyn ArrayBuilder>>;
+    /// Flushes and closes the destination, it is called once every partition is written.
+    fn finish(&self) -> Result<(), String>;
+}
+
+/// The state of a writer that is created with the schema of the first batch, as the
+/// exact Arrow schema is only known once the builders are finished.
+pub(crate) enum WriterState<W, T> {
+    /// Waiting for the first batch, with the sink the writer will write to.
+    Pending(W),
+    Writing(T),
+    Finished,
+}
+
+impl<W, T> WriterState<W, T> {
+    /// Returns the writer, creating it from the sink with `create` on the first batch.
+    pub(crate) fn writer(
+        &mut self,
+        create: impl FnOnce(W) -> Result<T, String>,
+    ) -> Result<&mut T, String> {
+        if let WriterState::Pending(_) = self {
+            let WriterState::Pending(sink) = std::mem::replace(self, WriterState::Finished) else {
+                unreachable!()
+            };
+            *self = WriterState::Writing(create(sink)?);
+        }
+        match self {
+            WriterState::Writing(writer) => Ok(writer),
+            _ => Err("Cannot write to a finished destination".to_string()),
+        }
+    }
+
+    /// Takes the writer to close it, `None` if nothing was written.
+    pub(crate) fn take(&mut self) -> Option<T> {
+        match std::mem::replace(self, WriterState::Finished) {
+            WriterState::Writing(writer) => Some(writer),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,217 @@
+use crate::destination::destination::{Destination, WriterState};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema as ArrowSchema};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::{FileWriter, StreamWriter};
+use arrow::record_batch::RecordBatch;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Writes the batches to an Arrow IPC file, also known as Feather v2.
+pub struct IpcFileDestination<W: Write + Send> {
+    state: Mutex<WriterState<W, FileWriter<W>>>,
+}
+
+impl<W: Write + Send> IpcFileDestination<W> {
+    pub fn new(sink: W) -> Self {
+        IpcFileDestination {
+            state: Mutex::new(WriterState::Pending(sink)),
+        }
+    }
+}
+
+impl IpcFileDestination<BufWriter<File>> {
+    /// Creates the file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> Self {
+        let file = File::create(path).expect("Could not create the Arrow IPC file");
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write + Send> Destination for IpcFileDestination<W> {
+    fn write(&self, batch: RecordBatch) -> Result<(), String> {
+        let batch = decode_dictionaries(batch)
+            .map_err(|e| format!("Could not write to the Arrow IPC file: {e}"))?;
+        self.state
+            .lock()
+            .unwrap()
+            .writer(|sink| {
+                FileWriter::try_new(sink, &batch.schema())
+                    .map_err(|e| format!("Could not create the Arrow IPC writer: {e}"))
+            })?
+            .write(&batch)
+            .map_err(|e| format!("Could not write to the Arrow IPC file: {e}"))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if let Some(mut writer) = self.state.lock().unwrap().take() {
+            writer
+                .finish()
+                .map_err(|e| format!("Could not finish the Arrow IPC file: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Casts the dictionary columns of `batch` to their values. Every batch has its own
+/// dictionaries, e.g. of `string_encoding='auto'`, and the IPC file format cannot replace a
+/// dictionary once it is written, unlike the stream format.
+fn decode_dictionaries(batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+    let schema = batch.schema();
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| matches!(field.data_type(), DataType::Dictionary(..)))
+    {
+        return Ok(batch);
+    }
+
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match field.data_type() {
+            DataType::Dictionary(_, values) => {
+                columns.push(cast(column, values)?);
+                fields.push(field.as_ref().clone().with_data_type(*values.clone()));
+            }
+            _ => {
+                columns.push(column.clone());
+                fields.push(field.as_ref().clone());
+            }
+        }
+    }
+    let schema = ArrowSchema::new_with_metadata(fields, schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+/// Writes the batches in the Arrow IPC streaming format, e.g. to stdout or a socket.
+pub struct IpcStreamDestination<W: Write + Send> {
+    state: Mutex<WriterState<W, StreamWriter<W>>>,
+}
+
+impl<W: Write + Send> IpcStreamDestination<W> {
+    pub fn new(sink: W) -> Self {
+        IpcStreamDestination {
+            state: Mutex::new(WriterState::Pending(sink)),
+        }
+    }
+}
+
+impl IpcStreamDestination<BufWriter<File>> {
+    /// Creates the file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> Self {
+        let file = File::create(path).expect("Could not create the Arrow IPC stream file");
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write + Send> Destination for IpcStreamDestination<W> {
+    fn write(&self, batch: RecordBatch) -> Result<(), String> {
+        self.state
+            .lock()
+            .unwrap()
+            .writer(|sink| {
+                StreamWriter::try_new(sink, &batch.schema())
+                    .map_err(|e| format!("Could not create the Arrow IPC stream writer: {e}"))
+            })?
+            .write(&batch)
+            .map_err(|e| format!("Could not write to the Arrow IPC stream: {e}"))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if let Some(mut writer) = self.state.lock().unwrap().take() {
+            writer
+                .finish()
+                .map_err(|e| format!("Could not finish the Arrow IPC stream: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{ArrayRef, DictionaryArray, Int32Array, StringArray};
+    use arrow::datatypes::Int32Type;
+    use arrow::ipc::reader::{FileReader, StreamReader};
+    use std::sync::Arc;
+
+    fn batch(values: Vec<i32>) -> RecordBatch {
+        let array: ArrayRef = Arc::new(Int32Array::from(values));
+        RecordBatch::try_from_iter([("id", array)]).unwrap()
+    }
+
+    #[test]
+    fn test_ipc_file_destination() {
+        let path = std::env::temp_dir().join("conecta_test_ipc_file_destination.arrow");
+        let destination = IpcFileDestination::create(&path);
+        destination.write(batch(vec![1, 2])).unwrap();
+        destination.write(batch(vec![3])).unwrap();
+        destination.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_ipc_stream_destination() {
+        let path = std::env::temp_dir().join("conecta_test_ipc_stream_destination.arrows");
+        let destination = IpcStreamDestination::create(&path);
+        destination.write(batch(vec![1, 2])).unwrap();
+        destination.write(batch(vec![])).unwrap();
+        destination.finish().unwrap();
+
+        let reader = StreamReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_ipc_file_destination_dictionaries() {
+        // Every partition has its own dictionary, which the IPC file format cannot replace.
+        let path = std::env::temp_dir().join("conecta_test_ipc_file_dictionaries.arrow");
+        let destination = IpcFileDestination::create(&path);
+        for values in [vec!["AIR", "RAIL", "AIR"], vec!["SHIP"]] {
+            let array: DictionaryArray<Int32Type> = values.into_iter().collect();
+            let array: ArrayRef = Arc::new(array);
+            let batch = RecordBatch::try_from_iter([("l_shipmode", array)]).unwrap();
+            destination.write(batch).unwrap();
+        }
+        destination.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        assert_eq!(reader.schema().field(0).data_type(), &DataType::Utf8);
+        let values: Vec<String> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let array = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                array
+                    .iter()
+                    .map(|v| v.unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(values, vec!["AIR", "RAIL", "AIR", "SHIP"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_after_finish() {
+        let destination = IpcStreamDestination::new(Vec::new());
+        destination.write(batch(vec![1])).unwrap();
+        destination.finish().unwrap();
+        assert_eq!(
+            destination.write(batch(vec![2])),
+            Err("Cannot write to a finished destination".to_string())
+        );
+    }
+}
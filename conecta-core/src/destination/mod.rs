@@ -1,5 +1,12 @@
 mod arrow;
+mod csv;
 mod destination;
+mod ipc;
+mod parquet;
 
-pub use crate::destination::arrow::get_arrow_builders;
+pub use crate::destination::arrow::{data_capacity, data_len, get_arrow_builders};
+pub use crate::destination::csv::CsvDestination;
+pub use crate::destination::ipc::{IpcFileDestination, IpcStreamDestination};
+pub use crate::destination::parquet::{ParquetConfig, ParquetDestination};
+pub use ::parquet::basic::Compression;
 pub use destination::Destination;
@@ -0,0 +1,108 @@
+use crate::destination::destination::{Destination, WriterState};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The options of the Parquet file.
+#[derive(Debug, Clone)]
+pub struct ParquetConfig {
+    pub compression: Compression,
+
+    /// Maximum number of rows in a row group, row groups are kept in memory until they are full.
+    pub max_row_group_size: usize,
+}
+
+impl Default for ParquetConfig {
+    fn default() -> Self {
+        ParquetConfig {
+            compression: Compression::SNAPPY,
+            max_row_group_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Writes the batches to a Parquet file.
+pub struct ParquetDestination<W: Write + Send> {
+    config: ParquetConfig,
+    state: Mutex<WriterState<W, ArrowWriter<W>>>,
+}
+
+impl<W: Write + Send> ParquetDestination<W> {
+    pub fn new(sink: W, config: ParquetConfig) -> Self {
+        ParquetDestination {
+            config,
+            state: Mutex::new(WriterState::Pending(sink)),
+        }
+    }
+}
+
+impl ParquetDestination<File> {
+    /// Creates the file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>, config: ParquetConfig) -> Self {
+        let file = File::create(path).expect("Could not create the Parquet file");
+        Self::new(file, config)
+    }
+}
+
+impl<W: Write + Send> Destination for ParquetDestination<W> {
+    fn write(&self, batch: RecordBatch) -> Result<(), String> {
+        self.state
+            .lock()
+            .unwrap()
+            .writer(|sink| {
+                let properties = WriterProperties::builder()
+                    .set_compression(self.config.compression)
+                    .set_max_row_group_size(self.config.max_row_group_size)
+                    .build();
+                ArrowWriter::try_new(sink, batch.schema(), Some(properties))
+                    .map_err(|e| format!("Could not create the Parquet writer: {e}"))
+            })?
+            .write(&batch)
+            .map_err(|e| format!("Could not write to the Parquet file: {e}"))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if let Some(writer) = self.state.lock().unwrap().take() {
+            writer
+                .close()
+                .map_err(|e| format!("Could not finish the Parquet file: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{ArrayRef, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parquet_destination() {
+        let path = std::env::temp_dir().join("conecta_test_parquet_destination.parquet");
+        let destination = ParquetDestination::create(
+            &path,
+            ParquetConfig {
+                compression: Compression::ZSTD(Default::default()),
+                max_row_group_size: 2,
+            },
+        );
+        for values in [vec!["AIR", "RAIL"], vec!["SHIP"]] {
+            let array: ArrayRef = Arc::new(StringArray::from(values));
+            let batch = RecordBatch::try_from_iter([("l_shipmode", array)]).unwrap();
+            destination.write(batch).unwrap();
+        }
+        destination.finish().unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 2);
+        assert_eq!(builder.metadata().file_metadata().num_rows(), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+}
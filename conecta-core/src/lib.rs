@@ -4,25 +4,45 @@ use postgres::NoTls;
 use r2d2_postgres::r2d2::Pool;
 use r2d2_postgres::PostgresConnectionManager;
 
+pub mod cancel;
+pub mod connection;
 pub mod destination;
+pub mod memory;
 pub mod metadata;
 pub mod partition;
 pub mod perf_logger;
+pub mod progress;
+pub mod report;
+pub mod retry;
+pub mod scan;
 pub mod schema;
+pub mod sink;
 pub mod source;
-
-use crate::metadata::{create_partition_plan, PartitionPlan};
+pub mod sql;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod trace;
+
+pub use crate::cancel::CancelToken;
+pub use crate::connection::{Connection, LoadConfig, PoolConfig};
+use crate::destination::Destination;
+use crate::metadata::PartitionPlan;
 use crate::partition::PartitionConfig;
 use crate::perf_logger::{perf_checkpoint, perf_start};
-use crate::source::{get_source, Source, SourceType};
+pub use crate::progress::{Progress, ProgressSnapshot};
+pub use crate::report::{LoadReport, PartitionReport};
+use crate::scan::Scan;
+use crate::schema::SchemaConfig;
+use crate::sink::postgres::PostgresSink;
+use crate::sink::WriteMode;
+use crate::source::{get_source, SourceType};
+#[cfg(feature = "async")]
+pub use crate::stream::{read_sql_async, LoadError, RecordBatchStream};
 
 use arrow::array::ArrayRef;
 use arrow::datatypes::{Field, Schema, SchemaRef};
 use arrow::record_batch::RecordBatch;
 
-use crate::source::postgres::PostgresSource;
-use log::debug;
-
 pub fn test_from_core() -> i32 {
     3
 }
@@ -32,18 +52,21 @@ pub fn test_from_core() -> i32 {
 ///
 /// All record batches are assumed to have the same schema, they are not concatenated
 /// to avoid memory copying.
-pub fn make_record_batches(arrays: Vec<Vec<ArrayRef>>, col_names: Vec<String>) -> Vec<RecordBatch> {
+pub fn make_record_batches(
+    arrays: Vec<Vec<ArrayRef>>,
+    schema: &crate::schema::Schema,
+) -> Vec<RecordBatch> {
     arrays
         .into_iter()
-        .map(|chunk| make_record_batch(chunk, col_names.clone()))
+        .map(|chunk| make_record_batch(chunk, schema))
         .collect::<Vec<RecordBatch>>()
 }
 
-pub fn make_record_batch(arrays: Vec<ArrayRef>, col_names: Vec<String>) -> RecordBatch {
+pub fn make_record_batch(arrays: Vec<ArrayRef>, schema: &crate::schema::Schema) -> RecordBatch {
     let fields: Vec<Field> = arrays
         .iter()
-        .zip(col_names)
-        .map(|(array, name)| Field::new(&name, array.data_type().clone(), true))
+        .zip(&schema.columns)
+        .map(|(array, column)| column.to_arrow_field(array.data_type().clone()))
         .collect();
 
     let schema = Arc::new(Schema::new(fields));
@@ -51,116 +74,147 @@ pub fn make_record_batch(arrays: Vec<ArrayRef>, col_names: Vec<String>) -> Recor
         .expect("Failed to create RecordBatch")
 }
 
+/// The number of connections of a load when the user does not give `max_pool_size`.
+fn default_pool_size(partition_config: &PartitionConfig) -> u32 {
+    // If the user does not provide max_pool_size, we will set it to the number of partitions
+    // that we will end up using, we cannot use any info from the partition_plan yet.
+    // We will use a dirty but correct calculation of how many threads/partitions we will use.
+    match partition_config.query.len() {
+        1 => partition_config.partition_num.unwrap_or(1) as u32,
+        queries => queries as u32,
+    }
+}
+
+/// Creates a `Connection` to `connection_string` for a single load, with a pool of
+/// `max_pool_size` connections.
+fn connect(connection_string: &str, max_pool_size: u32) -> Connection {
+    Connection::new(
+        connection_string,
+        PoolConfig {
+            max_size: max_pool_size,
+            ..PoolConfig::default()
+        },
+    )
+}
+
 /// Wrapper to get a partition plan, so other libraries (conecta-python) can create a partition
 /// plan without the required dependencies.
 pub fn _create_partition_plan(
-    // Source.
     connection_string: &str,
+    partition_config: PartitionConfig,
+    max_pool_size: Option<u32>,
+    explain: bool,
+) -> PartitionPlan {
+    let max_pool_size = max_pool_size.unwrap_or_else(|| default_pool_size(&partition_config));
 
-    // Partition Configuration.
-    query: Vec<String>,
-    partition_on: Option<String>,
-    partition_range: Option<(i64, i64)>,
-    partition_num: Option<u16>,
+    connect(connection_string, max_pool_size).plan(partition_config, explain)
+}
 
-    // Extra configuration.
+/// Loads the queries of `load_config` to Arrow arrays, every partition is a `Vec<ArrayRef>`. It
+/// creates a new pool of connections, use `Connection` to reuse it across loads.
+///
+/// Partitions that fail with a transient error, e.g. a connection reset, are retried as configured
+/// by `load_config.retry`. Panics if `cancel_token` is cancelled or times out, the running queries
+/// are cancelled. The decoded rows are added to `progress`, which another thread can read while it
+/// loads.
+///
+/// Returns the `LoadReport` of the load as well, with its timings and sizes.
+pub fn read_sql(
+    connection_string: &str,
+    load_config: LoadConfig,
     max_pool_size: Option<u32>,
-    preallocation: bool,
-) -> PartitionPlan {
-    let partition_config = PartitionConfig::new(
-        query.clone(),
-        partition_on,
-        partition_num,
-        partition_range,
-        preallocation,
-    );
+    cancel_token: &CancelToken,
+    progress: &Progress,
+) -> (Vec<Vec<ArrayRef>>, crate::schema::Schema, LoadReport) {
+    let max_pool_size =
+        max_pool_size.unwrap_or_else(|| default_pool_size(&load_config.partition_config));
 
-    let max_pool_size = max_pool_size.unwrap_or_else(|| {
-        // If the user does not provide max_pool_size, we will set it to the number of partitions
-        // that we will end up using, we cannot use any info from the partition_plan yet.
-        // We will use a dirty but correct calculation of how many threads/partitions we will use.
-        match query.len() {
-            1 => partition_num.unwrap_or(1) as u32,
-            _ => query.len() as u32,
-        }
-    });
+    connect(connection_string, max_pool_size).read_sql(load_config, cancel_token, progress)
+}
 
-    perf_checkpoint("Validating user parameters", false);
+/// Like `read_sql` but the data is written to `destination`, e.g. a Parquet file, as it is
+/// loaded, every partition keeps at most `batch_size` rows in memory.
+///
+/// All the columns are written as nullable, a column that is `NOT NULL` in its table can have
+/// nulls in the result, e.g. in outer joins, which is only known once everything is loaded.
+pub fn export_sql(
+    connection_string: &str,
+    load_config: LoadConfig,
+    max_pool_size: Option<u32>,
+    destination: &dyn Destination,
+    batch_size: usize,
+    cancel_token: &CancelToken,
+    progress: &Progress,
+) -> (crate::schema::Schema, LoadReport) {
+    let max_pool_size =
+        max_pool_size.unwrap_or_else(|| default_pool_size(&load_config.partition_config));
+
+    connect(connection_string, max_pool_size).export_sql(
+        load_config,
+        destination,
+        batch_size,
+        cancel_token,
+        progress,
+    )
+}
 
-    let source_type = get_source(connection_string, None);
-    let source: Box<dyn Source>;
+/// Returns the schema `query` with the values of its `params` would be loaded with by
+/// `read_sql`, without loading it.
+pub fn read_schema(
+    connection_string: &str,
+    query: &str,
+    params: &[String],
+    schema_config: &SchemaConfig,
+) -> crate::schema::Schema {
+    connect(connection_string, 1).schema(query, params, schema_config)
+}
 
-    match source_type {
-        SourceType::Postgres => {
-            let manager = PostgresConnectionManager::new(connection_string.parse().unwrap(), NoTls);
-            let pool = Pool::builder()
-                .max_size(max_pool_size)
-                .build(manager)
-                .expect("Could not create a pool of connections");
-            source = Box::new(PostgresSource { pool })
-        }
+/// Returns the SQL query of `scan` for the database of `connection_string`, without connecting
+/// to it, with the values of its parameters, see `PartitionConfig::with_params`.
+pub fn scan_query(connection_string: &str, scan: &Scan) -> (String, Vec<String>) {
+    match get_source(connection_string, None) {
+        SourceType::Postgres => crate::sql::scan_query(scan),
         _ => panic!("Source is not supported"),
     }
-    create_partition_plan(&source, partition_config)
 }
 
-pub fn read_sql(
-    // Source.
-    connection_string: &str,
-
-    // Partition Configuration.
-    query: Vec<String>,
-    partition_on: Option<String>,
-    partition_range: Option<(i64, i64)>,
-    partition_num: Option<u16>,
+/// Rows written by every connection of `write_sql` when `max_pool_size` is not given, so small
+/// writes do not open a connection per thread.
+const MIN_ROWS_PER_CONNECTION: usize = 100_000;
 
-    // Extra configuration.
+/// Writes `batches` to the table `table` of the database in `connection_string`, see
+/// `WriteMode` for what happens if the table exists. Batches are written in parallel by
+/// `max_pool_size` connections, by default one per `MIN_ROWS_PER_CONNECTION` rows up to one
+/// per rayon thread.
+///
+/// Returns the number of rows written.
+pub fn write_sql(
+    connection_string: &str,
+    table: &str,
+    batches: Vec<RecordBatch>,
+    mode: WriteMode,
     max_pool_size: Option<u32>,
-    preallocation: bool,
-) -> (Vec<Vec<ArrayRef>>, crate::schema::Schema) {
+) -> u64 {
     perf_start();
 
-    let partition_config = PartitionConfig::new(
-        query.clone(),
-        partition_on,
-        partition_num,
-        partition_range,
-        preallocation,
-    );
-
     let max_pool_size = max_pool_size.unwrap_or_else(|| {
-        // If the user does not provide max_pool_size, we will set it to the number of partitions
-        // that we will end up using, we cannot use any info from the partition_plan yet.
-        // We will use a dirty but correct calculation of how many threads/partitions we will use.
-        match query.len() {
-            1 => partition_num.unwrap_or(1) as u32,
-            _ => query.len() as u32,
-        }
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        rows.div_ceil(MIN_ROWS_PER_CONNECTION)
+            .clamp(1, rayon::current_num_threads()) as u32
     });
 
-    perf_checkpoint("Validating user parameters", false);
-
-    let source_type = get_source(connection_string, None);
-    let source: Box<dyn Source>;
-
-    match source_type {
+    let rows = match get_source(connection_string, None) {
         SourceType::Postgres => {
             let manager = PostgresConnectionManager::new(connection_string.parse().unwrap(), NoTls);
             let pool = Pool::builder()
                 .max_size(max_pool_size)
                 .build(manager)
                 .expect("Could not create a pool of connections");
-            source = Box::new(PostgresSource { pool })
+            PostgresSink { pool }.write(table, batches, mode)
         }
         _ => panic!("Source is not supported"),
-    }
-
-    let partition_plan = create_partition_plan(&source, partition_config);
-
-    debug!("{:?}", partition_plan);
-
-    perf_checkpoint("Created query plan", true);
+    };
 
-    let schema: crate::schema::Schema = source.get_schema_of(query.clone().get(0).unwrap());
-    source.process_partition_plan(partition_plan, schema)
+    perf_checkpoint(&format!("Wrote {} rows to {}", rows, table), true);
+    rows
 }
@@ -0,0 +1,148 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The bytes currently allocated through a `CountingAlloc`.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// The most bytes allocated through a `CountingAlloc` at once since the last
+/// `reset_peak_usage`, zero if no `CountingAlloc` is installed.
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts the bytes allocated by another allocator, `System` by
+/// default, so conecta can log and report the memory usage of loads.
+///
+/// It is installed by the `track-memory` feature, programs that use their own allocator can
+/// wrap it instead of enabling the feature:
+///
+/// ```text
+/// #[global_allocator]
+/// static GLOBAL: CountingAlloc<MiMalloc> = CountingAlloc::new(MiMalloc);
+/// ```
+///
+/// Without a `CountingAlloc` memory is not tracked and allocations have no overhead.
+pub struct CountingAlloc<A = System> {
+    allocator: A,
+}
+
+impl<A> CountingAlloc<A> {
+    pub const fn new(allocator: A) -> Self {
+        CountingAlloc { allocator }
+    }
+}
+
+fn allocated(size: usize) {
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn deallocated(size: usize) {
+    CURRENT.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.allocator.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.allocator.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.allocator.dealloc(ptr, layout);
+        deallocated(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.allocator.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                allocated(new_size - layout.size());
+            } else {
+                deallocated(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[cfg(feature = "track-memory")]
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new(System);
+
+/// Returns whether a `CountingAlloc` is installed, i.e. it allocated anything.
+pub fn is_tracked() -> bool {
+    PEAK.load(Ordering::Relaxed) > 0
+}
+
+/// Returns the bytes currently allocated, `None` if memory is not tracked.
+pub fn current_usage() -> Option<usize> {
+    is_tracked().then(|| CURRENT.load(Ordering::Relaxed))
+}
+
+/// Returns the most bytes allocated at once since the last `reset_peak_usage`, `None` if
+/// memory is not tracked.
+pub fn peak_usage() -> Option<usize> {
+    is_tracked().then(|| PEAK.load(Ordering::Relaxed))
+}
+
+/// Starts measuring the peak from the current usage.
+pub fn reset_peak_usage() {
+    if is_tracked() {
+        // The peak is never zero once tracked, zero means untracked.
+        PEAK.store(CURRENT.load(Ordering::Relaxed).max(1), Ordering::Relaxed);
+    }
+}
+
+/// Formats `bytes` as megabytes for logs, e.g. `12.5MB`, or `untracked` if memory is not
+/// tracked.
+pub fn as_mb(bytes: Option<usize>) -> String {
+    match bytes {
+        Some(bytes) => format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0)),
+        None => "untracked".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // With the feature every allocation of the tests is counted as well.
+    #[cfg(not(feature = "track-memory"))]
+    #[test]
+    fn test_counting_alloc() {
+        let alloc = CountingAlloc::new(System);
+        let layout = Layout::from_size_align(1024, 8).unwrap();
+
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            assert!(is_tracked());
+            let current = current_usage().unwrap();
+            assert!(peak_usage().unwrap() >= current);
+
+            let ptr = alloc.realloc(ptr, layout, 4096);
+            assert_eq!(current_usage().unwrap(), current + 3072);
+            assert!(peak_usage().unwrap() >= current + 3072);
+
+            alloc.dealloc(ptr, Layout::from_size_align(4096, 8).unwrap());
+            assert_eq!(current_usage().unwrap(), current - 1024);
+        }
+
+        reset_peak_usage();
+        assert!(peak_usage().unwrap() >= 1);
+    }
+
+    #[test]
+    fn test_as_mb() {
+        assert_eq!(as_mb(Some(3 * 1024 * 1024 / 2)), "1.5MB");
+        assert_eq!(as_mb(None), "untracked");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::partition::{created_bounded_queries, PartitionConfig};
+use crate::partition::{create_key_bounded_queries, created_bounded_queries, PartitionConfig};
 use crate::source::Source;
 use serde::Serialize;
 
@@ -7,6 +7,9 @@ pub enum NeededMetadataFromSource {
     Count,
     MinMax,
     CountAndMinMax,
+
+    /// The first keys of every partition, when partitioning on several keys.
+    CountAndKeyBounds,
     None,
 }
 
@@ -25,10 +28,11 @@ pub enum QueryPartitioningMode {
 
 /// Creates a partition plan given the `Source` and the user configuration `PartitionConfig`.
 pub fn create_partition_plan(
-    source: &Box<dyn Source>,
+    source: &dyn Source,
     partition_config: PartitionConfig,
 ) -> PartitionPlan {
     let data_queries: Vec<String>;
+    let mut data_params: Vec<Vec<String>> = vec![];
 
     // We set min/max as it might be needed for the count.
     let (mut min_value, mut max_value) = match partition_config.partition_range {
@@ -45,48 +49,111 @@ pub fn create_partition_plan(
         _ => &source.merge_queries(&partition_config.query),
     };
 
+    let mut key_bounds = vec![];
     match partition_config.needed_metadata_from_source {
         NeededMetadataFromSource::CountAndMinMax | NeededMetadataFromSource::MinMax => {
-            (min_value, max_value) =
-                source.fetch_min_max(query, partition_config.partition_on.as_deref().unwrap());
+            (min_value, max_value) = source.fetch_min_max(
+                query,
+                &partition_config.params,
+                partition_config.partition_on.as_deref().unwrap(),
+            );
+        }
+        NeededMetadataFromSource::CountAndKeyBounds => {
+            key_bounds = source.fetch_key_bounds(
+                query,
+                &partition_config.params,
+                partition_config.partition_on.as_deref().unwrap(),
+                partition_config.partition_num.unwrap(),
+            );
         }
         _ => {}
     }
 
     match partition_config.query_partition_mode {
-        QueryPartitioningMode::OnePartitionedQuery => {
-            // Create the bounded queries.
-            data_queries = created_bounded_queries(
+        QueryPartitioningMode::OnePartitionedQuery
+            if partition_config.needed_metadata_from_source
+                == NeededMetadataFromSource::CountAndKeyBounds =>
+        {
+            (data_queries, data_params) = create_key_bounded_queries(
                 source,
                 partition_config.query[0].as_str(),
-                &partition_config.partition_on.clone().unwrap(),
-                partition_config.partition_num.unwrap(),
-                min_value.expect("should have a valid min at this point"),
-                max_value.expect("should have a valid max at this point"),
+                &partition_config.params,
+                partition_config.partition_on.as_deref().unwrap(),
+                &key_bounds,
             )
         }
+        QueryPartitioningMode::OnePartitionedQuery => match min_value.zip(max_value) {
+            // Create the bounded queries.
+            Some((min, max)) => {
+                data_queries = created_bounded_queries(
+                    source,
+                    partition_config.query[0].as_str(),
+                    &partition_config.partition_on.clone().unwrap(),
+                    partition_config.partition_num.unwrap(),
+                    min,
+                    max,
+                )
+            }
+            // The query has no rows or all its keys are null, there is nothing to partition.
+            None => data_queries = vec![partition_config.query[0].clone()],
+        },
 
         // If we don't need to create any query (by partitioning it), we just set query_data
         // to whatever query(s) the user provided.
         _ => data_queries = Vec::from(partition_config.query.clone()),
     }
+    // The other queries only have the parameters of the user.
+    data_params.resize(data_queries.len(), partition_config.params.clone());
     // todo: remove or followup.
     let counts = vec![];
     PartitionPlan {
         min_value,
         max_value,
+        key_bounds,
         counts,
         metadata_query: "fake".to_string(),
         data_queries,
+        data_params,
+        explain: vec![],
         partition_config,
     }
 }
 
+/// Sets `PartitionPlan::explain` with the plan the database would run every data query with,
+/// without running them.
+pub fn explain_partition_plan(source: &dyn Source, partition_plan: &mut PartitionPlan) {
+    partition_plan.explain = partition_plan
+        .data_queries
+        .iter()
+        .zip(&partition_plan.data_params)
+        .map(|(query, params)| source.explain(query, params))
+        .collect();
+}
+
+/// What the planner of the database estimates for a data query, see `Source::explain`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionExplain {
+    pub estimated_rows: f64,
+
+    /// The cost to return the first row and all the rows, in the units of the planner.
+    pub startup_cost: f64,
+    pub total_cost: f64,
+
+    /// The first scan of the plan, e.g. "Seq Scan" or "Index Scan", and the table and index it
+    /// reads. A partition that does not hit an index on `partition_on` scans the whole table.
+    pub scan_type: Option<String>,
+    pub relation_name: Option<String>,
+    pub index_name: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PartitionPlan {
     pub min_value: Option<i64>,
     pub max_value: Option<i64>,
 
+    /// The first keys of every partition as text, only when partitioning on several keys.
+    pub key_bounds: Vec<Vec<String>>,
+
     /// Total count of rows per partition. i.e. [10_000, 20_000]
     pub counts: Vec<i64>,
 
@@ -96,6 +163,14 @@ pub struct PartitionPlan {
     /// The query(s) that will be used to fetch the data, with partition included if requested.
     pub data_queries: Vec<String>,
 
+    /// The values of the `$1`, `$2`, ... parameters of every data query as text, Postgres parses
+    /// them as the type of their parameter. Only the queries of `key_bounds` have parameters.
+    pub data_params: Vec<Vec<String>>,
+
+    /// The plan of every data query, only if the partition plan was explained, see
+    /// `explain_partition_plan`.
+    pub explain: Vec<PartitionExplain>,
+
     /// The configuration used to generate the QueryPlan. It is validated user's input.
     pub partition_config: PartitionConfig,
 }
@@ -103,7 +178,11 @@ pub struct PartitionPlan {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::Schema;
+    use crate::destination::Destination;
+    use crate::report::PartitionReport;
+    use crate::scan::Scan;
+    use crate::schema::{Schema, SchemaConfig};
+    use crate::source::LoadContext;
     use arrow::array::ArrayRef;
 
     #[derive(Debug)]
@@ -112,11 +191,31 @@ mod tests {
     impl Source for DummySource {
         fn process_partition_plan(
             &self,
-            partition_plan: PartitionPlan,
-            schema: Schema,
-        ) -> (Vec<Vec<ArrayRef>>, Schema) {
+            _partition_plan: PartitionPlan,
+            _schema: Schema,
+            _context: &LoadContext,
+        ) -> (Vec<Vec<ArrayRef>>, Schema, Vec<PartitionReport>) {
+            todo!()
+        }
+        fn write_partition_plan(
+            &self,
+            _partition_plan: PartitionPlan,
+            _schema: Schema,
+            _destination: &dyn Destination,
+            _batch_size: usize,
+            _context: &LoadContext,
+        ) -> (Schema, Vec<PartitionReport>) {
             todo!()
         }
+        fn explain(&self, _query: &str, _params: &[String]) -> PartitionExplain {
+            todo!()
+        }
+        fn estimate_rows(&self, _query: &str, _params: &[String]) -> Option<u64> {
+            None
+        }
+        fn protocol(&self) -> &str {
+            "dummy"
+        }
         fn wrap_query_with_bounds(
             &self,
             query: &str,
@@ -126,30 +225,75 @@ mod tests {
         ) -> String {
             "wrapped()".to_string()
         }
-        fn merge_queries(&self, queries: &Vec<String>) -> String {
+        fn wrap_query_with_key_bounds(
+            &self,
+            _query: &str,
+            params: &[String],
+            _partition_on: &str,
+            start: &[String],
+            _stop: Option<&[String]>,
+        ) -> (String, Vec<String>) {
+            ("wrapped()".to_string(), [params, start].concat())
+        }
+        fn wrap_query_with_null_keys(&self, _query: &str, _partition_on: &str) -> String {
+            "null_keys()".to_string()
+        }
+        fn merge_queries(&self, _queries: &Vec<String>) -> String {
             "".to_string()
         }
-        fn get_schema_query(&self, original_query: &str) -> String {
+        fn get_schema_query(&self, _original_query: &str) -> String {
             "some_schema_query".to_string()
         }
-        fn get_table_name(&self, query: &str) -> String {
-            "some_table_name".to_string()
+        fn get_table_names(&self, _query: &str) -> Vec<String> {
+            vec!["some_table_name".to_string()]
         }
-        fn fetch_min_max(&self, query: &str, column: &str) -> (Option<i64>, Option<i64>) {
+        fn fetch_min_max(
+            &self,
+            _query: &str,
+            _params: &[String],
+            _column: &str,
+        ) -> (Option<i64>, Option<i64>) {
             (Some(1), Some(10))
         }
+        fn fetch_key_bounds(
+            &self,
+            _query: &str,
+            _params: &[String],
+            _partition_on: &str,
+            partition_num: u16,
+        ) -> Vec<Vec<String>> {
+            vec![vec!["1".to_string(), "1".to_string()]; partition_num as usize - 1]
+        }
 
         fn validate(&self) {
             todo!()
         }
 
-        fn get_schema_of(&self, query: &str) -> Schema {
+        fn get_schema_of(
+            &self,
+            _query: &str,
+            _params: &[String],
+            _schema_config: &SchemaConfig,
+        ) -> Schema {
             todo!()
         }
 
-        fn get_min_max_query(&self, query: &str, col: &str) -> String {
+        fn get_min_max_query(&self, _query: &str, _col: &str) -> String {
             "min_max_query".to_string()
         }
+
+        fn get_key_bounds_query(
+            &self,
+            _query: &str,
+            _partition_on: &str,
+            _partition_num: u16,
+        ) -> String {
+            "key_bounds_query".to_string()
+        }
+
+        fn get_scan_query(&self, _scan: &Scan) -> (String, Vec<String>) {
+            ("scan_query".to_string(), vec![])
+        }
     }
 
     #[test]
@@ -164,7 +308,7 @@ mod tests {
             false,
         );
 
-        let query_plan = create_partition_plan(&source, partition_config);
+        let query_plan = create_partition_plan(source.as_ref(), partition_config);
         assert_eq!(query_plan.min_value, Some(1));
         assert_eq!(query_plan.max_value, Some(10));
         // assert_eq!(query_plan.counts, 10);
@@ -174,6 +318,54 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_create_query_plan_one_partitioned_query_keys() {
+        let source: Box<dyn Source> = Box::new(DummySource);
+        let partition_config = PartitionConfig::new(
+            vec!["select * from l_orderkey".to_string()],
+            Some("col_a, col_b".to_string()),
+            Some(4),
+            None,
+            false,
+        );
+
+        // The source has fewer rows than partitions.
+        let query_plan = create_partition_plan(source.as_ref(), partition_config);
+        assert_eq!(query_plan.min_value, None);
+        assert_eq!(query_plan.key_bounds.len(), 3);
+        // The rows with null keys are in the last query, without parameters.
+        assert_eq!(
+            query_plan.data_queries,
+            ["wrapped()", "wrapped()", "wrapped()", "null_keys()"]
+        );
+        assert_eq!(
+            query_plan.data_params,
+            [vec!["1", "1"], vec!["1", "1"], vec!["1", "1"], vec![]]
+        );
+    }
+
+    #[test]
+    fn test_create_query_plan_params() {
+        let source: Box<dyn Source> = Box::new(DummySource);
+        let params = vec!["AIR".to_string()];
+        let config = |partition_on: &str| {
+            PartitionConfig::new(
+                vec!["select * from lineitem where l_shipmode = $1".to_string()],
+                Some(partition_on.to_string()),
+                Some(2),
+                None,
+                false,
+            )
+            .with_params(params.clone())
+        };
+
+        // Every query has the parameters of the user, the key bounds come after them.
+        let query_plan = create_partition_plan(source.as_ref(), config("col"));
+        assert_eq!(query_plan.data_params, [params.clone(), params.clone()]);
+        let query_plan = create_partition_plan(source.as_ref(), config("col_a, col_b"));
+        assert_eq!(query_plan.data_params, [vec!["AIR", "1", "1"], vec!["AIR"]]);
+    }
+
     #[test]
     fn test_create_query_plan_one_partitioned_query_ranged() {
         let source: Box<dyn Source> = Box::new(DummySource);
@@ -187,7 +379,7 @@ mod tests {
             false,
         );
 
-        let query_plan = create_partition_plan(&source, partition_config);
+        let query_plan = create_partition_plan(source.as_ref(), partition_config);
         assert_eq!(query_plan.min_value, Some(partition_range.unwrap().0));
         assert_eq!(query_plan.max_value, Some(partition_range.unwrap().1));
         // assert_eq!(query_plan.counts, 10);
@@ -209,7 +401,7 @@ mod tests {
             false,
         );
 
-        let query_plan = create_partition_plan(&source, partition_config);
+        let query_plan = create_partition_plan(source.as_ref(), partition_config);
         assert_eq!(query_plan.min_value, None);
         assert_eq!(query_plan.max_value, None);
         // assert_eq!(query_plan.counts, 10);
@@ -228,7 +420,7 @@ mod tests {
             false,
         );
 
-        let query_plan = create_partition_plan(&source, partition_config);
+        let query_plan = create_partition_plan(source.as_ref(), partition_config);
         println!("{:#?}", query_plan);
         assert_eq!(query_plan.min_value, Some(partition_range.unwrap().0));
         assert_eq!(query_plan.max_value, Some(partition_range.unwrap().1));
@@ -252,7 +444,7 @@ mod tests {
             false,
         );
 
-        let query_plan = create_partition_plan(&source, partition_config);
+        let query_plan = create_partition_plan(source.as_ref(), partition_config);
         assert_eq!(query_plan.min_value, None);
         assert_eq!(query_plan.max_value, None);
         // assert_eq!(query_plan.counts, 10);
@@ -1,5 +1,6 @@
 use crate::metadata::{NeededMetadataFromSource, QueryPartitioningMode};
 use crate::source::Source;
+use crate::sql::{parse_partition_on, parse_query};
 use serde::Serialize;
 
 /// Represents the configuration that will be used for the data load.
@@ -12,6 +13,10 @@ pub struct PartitionConfig {
     pub partition_range: Option<(i64, i64)>,
     pub preallocation: bool,
 
+    /// The values of the `$1`, `$2`, ... parameters of `query`, as text, see
+    /// `PartitionConfig::with_params`.
+    pub params: Vec<String>,
+
     // Computed by us from the user parameters.
     pub needed_metadata_from_source: NeededMetadataFromSource,
     pub query_partition_mode: QueryPartitioningMode,
@@ -29,6 +34,15 @@ impl PartitionConfig {
             panic!("must pass some queries!")
         }
 
+        // Rejects several statements, statements that modify data or a partition_on that is not a
+        // column or an expression of columns before running anything.
+        for query in &query {
+            parse_query(query);
+        }
+        let keys = partition_on
+            .as_deref()
+            .map_or(0, |p| parse_partition_on(p).len());
+
         if (partition_num.is_some() || partition_on.is_some() || partition_range.is_some())
             && query.len() > 1
         {
@@ -53,6 +67,13 @@ impl PartitionConfig {
             panic!("You passed a partition_range but did not specified a partition_on.")
         }
 
+        if partition_range.is_some() && keys > 1 {
+            panic!(
+                "partition_range is the (min, max) of one partition_on key, it cannot be used \
+                with {keys} keys"
+            )
+        }
+
         // Check that min/max values are valid.
         if let Some((min, max)) = partition_range {
             if min >= max {
@@ -65,7 +86,10 @@ impl PartitionConfig {
         }
 
         let needed_metadata_from_source = {
-            if partition_range.is_none() && partition_num.is_some() && partition_on.is_some() {
+            if partition_num.is_some() && keys > 1 {
+                NeededMetadataFromSource::CountAndKeyBounds
+            } else if partition_range.is_none() && partition_num.is_some() && partition_on.is_some()
+            {
                 NeededMetadataFromSource::CountAndMinMax
             } else {
                 NeededMetadataFromSource::Count
@@ -86,7 +110,24 @@ impl PartitionConfig {
             needed_metadata_from_source,
             query_partition_mode: partition_mode,
             preallocation,
+            params: vec![],
+        }
+    }
+
+    /// Sets the values of the parameters of the query, e.g. the filters of `sql::scan_query`,
+    /// every query that reads it is run with them. Postgres parses them as the type they are
+    /// compared with.
+    ///
+    /// Panics if there are several queries, they cannot share the parameters.
+    pub fn with_params(mut self, params: Vec<String>) -> Self {
+        if !params.is_empty() && self.query.len() > 1 {
+            panic!(
+                "Parameters can only be given with one query, got {} queries",
+                self.query.len()
+            )
         }
+        self.params = params;
+        self
     }
 }
 
@@ -114,7 +155,7 @@ fn bounds(min: i64, max: i64, n: usize) -> Vec<(i64, i64)> {
 }
 
 pub fn created_bounded_queries(
-    source: &Box<dyn Source>,
+    source: &dyn Source,
     query: &str,
     partition_on: &str,
     partition_num: u16,
@@ -133,6 +174,34 @@ pub fn created_bounded_queries(
     data_queries
 }
 
+/// Returns a query per partition of `key_bounds`, from its first keys to the ones of the next
+/// partition, see `Source::wrap_query_with_key_bounds`, with its parameters after the `params`
+/// of `query`. The rows where a key is null are in one more query, they have no place in the
+/// order of the keys. If there are no bounds, every key of `query` is null and it is the only
+/// query.
+pub fn create_key_bounded_queries(
+    source: &dyn Source,
+    query: &str,
+    params: &[String],
+    partition_on: &str,
+    key_bounds: &[Vec<String>],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    if key_bounds.is_empty() {
+        return (vec![query.to_string()], vec![params.to_vec()]);
+    }
+    let (mut queries, mut data_params): (Vec<String>, Vec<Vec<String>>) = key_bounds
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let stop = key_bounds.get(i + 1).map(Vec::as_slice);
+            source.wrap_query_with_key_bounds(query, params, partition_on, start, stop)
+        })
+        .unzip();
+    queries.push(source.wrap_query_with_null_keys(query, partition_on));
+    data_params.push(params.to_vec());
+    (queries, data_params)
+}
+
 #[cfg(test)]
 mod create_bound_tests {
     use super::*;
@@ -264,6 +333,19 @@ mod config_partition_tests {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "Parameters can only be given with one query")]
+    fn test_params_of_several_queries_panics() {
+        PartitionConfig::new(
+            vec!["SELECT * FROM a".to_string(), "SELECT * FROM b".to_string()],
+            None,
+            None,
+            None,
+            false,
+        )
+        .with_params(vec!["1".to_string()]);
+    }
+
     #[test]
     #[should_panic]
     fn test_partition_num_without_partition_on_panics() {
@@ -298,4 +380,47 @@ mod config_partition_tests {
             false,
         );
     }
+
+    #[test]
+    fn test_partition_on_keys() {
+        let config = PartitionConfig::new(
+            vec!["SELECT * FROM data".to_string()],
+            Some("tenant_id, id".to_string()),
+            Some(4),
+            None,
+            false,
+        );
+        assert_eq!(
+            config.needed_metadata_from_source,
+            NeededMetadataFromSource::CountAndKeyBounds
+        );
+        assert_eq!(
+            config.query_partition_mode,
+            QueryPartitioningMode::OnePartitionedQuery
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "it cannot be used with 2 keys")]
+    fn test_partition_on_keys_with_range_panics() {
+        PartitionConfig::new(
+            vec!["SELECT * FROM data".to_string()],
+            Some("tenant_id, id".to_string()),
+            Some(4),
+            Some((0, 100)),
+            false,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "partition_on has to be a column of the query")]
+    fn test_partition_on_not_a_column_panics() {
+        PartitionConfig::new(
+            vec!["SELECT * FROM data".to_string()],
+            Some("value) from data; drop table data; --".to_string()),
+            Some(2),
+            None,
+            false,
+        );
+    }
 }
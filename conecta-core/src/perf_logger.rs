@@ -1,8 +1,7 @@
-use peak_alloc::PeakAlloc;
 use std::sync::{Mutex, OnceLock};
 
-#[global_allocator]
-pub static PEAK_ALLOC: PeakAlloc = PeakAlloc;
+use crate::memory::{as_mb, current_usage, peak_usage};
+
 static PERF_LOGGER: OnceLock<Mutex<PerfLogger>> = OnceLock::new();
 
 use std::time::{Duration, Instant};
@@ -14,8 +13,11 @@ pub struct PerfLogger {
 }
 
 impl PerfLogger {
+    /// Starts measuring from now, checkpoints are counted again from the first one.
     pub fn start(&mut self) {
         self.start = Some(Instant::now());
+        self.last = Some(Duration::new(0, 0));
+        self.checkpoint_count = 0;
     }
     pub fn new() -> Self {
         PerfLogger {
@@ -37,8 +39,7 @@ impl PerfLogger {
 
     pub fn log_checkpoint(&mut self, message: &str, with_memory: bool) {
         let memory = if with_memory {
-            let string = format!("{}Mb RAM", PEAK_ALLOC.current_usage_as_mb());
-            string
+            format!("{} RAM", as_mb(current_usage()))
         } else {
             String::new()
         };
@@ -62,7 +63,7 @@ impl PerfLogger {
     }
 
     pub fn log_peak_memory(&self) {
-        log::debug!("peak_mem_usage: {}MB", PEAK_ALLOC.peak_usage_as_mb())
+        log::debug!("peak_mem_usage: {}", as_mb(peak_usage()))
     }
 
     pub fn elapsed(&self) -> Duration {
@@ -91,22 +92,16 @@ pub fn perf_peak_memory() {
 }
 
 pub fn log_memory() {
-    log::debug!(
-        "[DEBUG] Current memory usage: {}MB",
-        PEAK_ALLOC.current_usage_as_mb()
-    )
+    log::debug!("[DEBUG] Current memory usage: {}", as_mb(current_usage()))
 }
 
 pub fn log_memory_with_message(message: &str) {
     log::debug!(
-        "[DEBUG] {message} | Current memory usage: {}MB",
-        PEAK_ALLOC.current_usage_as_mb()
+        "[DEBUG] {message} | Current memory usage: {}",
+        as_mb(current_usage())
     )
 }
 
 pub fn log_peak_memory() {
-    log::debug!(
-        "[DEBUG] Peak memory usage: {}MB",
-        PEAK_ALLOC.peak_usage_as_mb()
-    )
+    log::debug!("[DEBUG] Peak memory usage: {}", as_mb(peak_usage()))
 }
@@ -0,0 +1,213 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// `PartitionProgress::count` of a partition whose count is not known.
+const UNKNOWN: u64 = u64::MAX;
+
+/// The progress of a partition, updated by the thread loading it.
+#[derive(Debug)]
+struct PartitionProgress {
+    rows: AtomicU64,
+    bytes: AtomicU64,
+
+    /// The rows of the partition, known if it runs a count query for preallocation.
+    count: AtomicU64,
+    finished: AtomicBool,
+}
+
+impl Default for PartitionProgress {
+    fn default() -> Self {
+        PartitionProgress {
+            rows: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            count: AtomicU64::new(UNKNOWN),
+            finished: AtomicBool::new(false),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    partitions: OnceLock<Vec<PartitionProgress>>,
+
+    /// The rows of the load estimated by the planner of the database.
+    estimated_rows: OnceLock<Option<u64>>,
+}
+
+/// The progress of a load, the rows and bytes decoded by every partition, which another thread
+/// can read with `snapshot` while it loads.
+///
+/// The default `Progress` tracks nothing, so loads that nobody watches do not count their bytes
+/// or ask the database for an estimate of their rows. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    state: Option<Arc<ProgressState>>,
+}
+
+/// The progress of a load at some point, see `Progress::snapshot`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressSnapshot {
+    /// The rows decoded so far.
+    pub rows: u64,
+
+    /// The bytes of the decoded rows, as sent by the database.
+    pub bytes: u64,
+
+    /// The rows of the whole load, exact if every partition counted its rows for preallocation,
+    /// otherwise estimated by the planner of the database. `None` if it is not known.
+    pub estimated_rows: Option<u64>,
+
+    /// The number of partitions, zero until the partition plan is created.
+    pub partitions: usize,
+    pub finished_partitions: usize,
+}
+
+impl Progress {
+    /// Creates a `Progress` that tracks the load it is given to.
+    pub fn new() -> Self {
+        Progress {
+            state: Some(Arc::default()),
+        }
+    }
+
+    pub fn is_tracked(&self) -> bool {
+        self.state.is_some()
+    }
+
+    fn partition(&self, index: usize) -> Option<&PartitionProgress> {
+        self.state.as_ref()?.partitions.get()?.get(index)
+    }
+
+    /// Starts tracking a load of `partitions` partitions, of `estimated_rows` rows.
+    pub fn start(&self, partitions: usize, estimated_rows: Option<u64>) {
+        if let Some(state) = &self.state {
+            let _ = state.partitions.set(
+                (0..partitions)
+                    .map(|_| PartitionProgress::default())
+                    .collect(),
+            );
+            let _ = state.estimated_rows.set(estimated_rows);
+        }
+    }
+
+    /// Starts the partition `index` again from zero rows, e.g. when it is retried.
+    pub fn restart_partition(&self, index: usize) {
+        if let Some(partition) = self.partition(index) {
+            partition.rows.store(0, Ordering::Relaxed);
+            partition.bytes.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Sets the exact number of rows of the partition `index`.
+    pub fn set_count(&self, index: usize, count: u64) {
+        if let Some(partition) = self.partition(index) {
+            partition.count.store(count, Ordering::Relaxed);
+        }
+    }
+
+    /// Adds `rows` decoded rows of `bytes` bytes to the partition `index`.
+    pub fn add(&self, index: usize, rows: u64, bytes: u64) {
+        if let Some(partition) = self.partition(index) {
+            partition.rows.fetch_add(rows, Ordering::Relaxed);
+            partition.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    pub fn finish_partition(&self, index: usize) {
+        if let Some(partition) = self.partition(index) {
+            partition.finished.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the progress of the load so far.
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let partitions = self
+            .state
+            .as_ref()
+            .and_then(|state| state.partitions.get())
+            .map_or(&[][..], Vec::as_slice);
+
+        let counts: Option<u64> = partitions
+            .iter()
+            .map(|partition| match partition.count.load(Ordering::Relaxed) {
+                UNKNOWN => None,
+                count => Some(count),
+            })
+            .sum();
+        let estimated_rows = match counts {
+            Some(counts) if !partitions.is_empty() => Some(counts),
+            _ => self
+                .state
+                .as_ref()
+                .and_then(|state| state.estimated_rows.get().copied().flatten()),
+        };
+
+        ProgressSnapshot {
+            rows: partitions
+                .iter()
+                .map(|partition| partition.rows.load(Ordering::Relaxed))
+                .sum(),
+            bytes: partitions
+                .iter()
+                .map(|partition| partition.bytes.load(Ordering::Relaxed))
+                .sum(),
+            estimated_rows,
+            partitions: partitions.len(),
+            finished_partitions: partitions
+                .iter()
+                .filter(|partition| partition.finished.load(Ordering::Relaxed))
+                .count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress() {
+        let progress = Progress::new();
+        assert_eq!(progress.snapshot().partitions, 0);
+
+        progress.start(2, Some(1000));
+        progress.clone().add(0, 10, 100);
+        progress.add(1, 5, 50);
+        progress.finish_partition(1);
+        assert_eq!(
+            progress.snapshot(),
+            ProgressSnapshot {
+                rows: 15,
+                bytes: 150,
+                estimated_rows: Some(1000),
+                partitions: 2,
+                finished_partitions: 1,
+            }
+        );
+
+        // A retried partition starts from zero.
+        progress.restart_partition(0);
+        assert_eq!(progress.snapshot().rows, 5);
+    }
+
+    #[test]
+    fn test_progress_counts() {
+        let progress = Progress::new();
+        progress.start(2, Some(1000));
+
+        // The estimate of the planner is used until every partition is counted.
+        progress.set_count(0, 10);
+        assert_eq!(progress.snapshot().estimated_rows, Some(1000));
+        progress.set_count(1, 20);
+        assert_eq!(progress.snapshot().estimated_rows, Some(30));
+    }
+
+    #[test]
+    fn test_progress_untracked() {
+        let progress = Progress::default();
+        progress.start(1, None);
+        progress.add(0, 10, 100);
+        assert!(!progress.is_tracked());
+        assert_eq!(progress.snapshot().rows, 0);
+    }
+}
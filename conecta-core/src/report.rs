@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Serializer};
+
+use crate::memory::{peak_usage, reset_peak_usage};
+
+/// Durations are serialized as seconds, e.g. `1.25`.
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+fn serialize_secs_opt<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serialize_secs(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The statistics of a load, returned by `read_sql` and `export_sql`.
+///
+/// Durations are serialized as seconds and sizes are in bytes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LoadReport {
+    /// How the rows were transferred, e.g. `binary` for Postgres' binary result format.
+    pub protocol: String,
+
+    /// Creating the partition plan, including the min/max query of `partition_on`.
+    #[serde(serialize_with = "serialize_secs")]
+    pub planning: Duration,
+
+    /// The metadata queries for the schema of the load.
+    #[serde(serialize_with = "serialize_secs")]
+    pub metadata: Duration,
+
+    /// The whole load, from planning until the last partition finished.
+    #[serde(serialize_with = "serialize_secs")]
+    pub total: Duration,
+
+    pub rows: usize,
+
+    /// The size of the loaded Arrow arrays.
+    pub bytes: usize,
+
+    /// The peak memory used by the process during the load, it includes other loads running at
+    /// the same time. `None` if memory is not tracked, see `memory::CountingAlloc`.
+    pub peak_memory: Option<usize>,
+
+    pub partitions: Vec<PartitionReport>,
+}
+
+/// The statistics of a partition of a `LoadReport`, the durations are since the partition
+/// started, of its last attempt if it was retried.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PartitionReport {
+    pub query: String,
+    pub attempts: u32,
+
+    /// The query was executed and the database started sending its rows.
+    #[serde(serialize_with = "serialize_secs")]
+    pub executed: Duration,
+
+    /// The first row was received, `None` if the partition had no rows.
+    #[serde(serialize_with = "serialize_secs_opt")]
+    pub first_row: Option<Duration>,
+
+    /// The last row was loaded.
+    #[serde(serialize_with = "serialize_secs")]
+    pub finished: Duration,
+
+    pub rows: usize,
+
+    /// The size of the loaded Arrow arrays, of all its batches if it was written in batches.
+    pub bytes: usize,
+
+    /// The bytes preallocated for variable-length data, e.g. strings.
+    pub preallocated: usize,
+}
+
+impl LoadReport {
+    /// Starts the report of a load, peak memory is measured from now.
+    pub(crate) fn start(protocol: &str) -> Self {
+        reset_peak_usage();
+        LoadReport {
+            protocol: protocol.to_string(),
+            ..LoadReport::default()
+        }
+    }
+
+    /// Adds the reports of the loaded `partitions`, the load started at `start`.
+    pub(crate) fn finish(&mut self, start: Instant, partitions: Vec<PartitionReport>) {
+        self.total = start.elapsed();
+        self.rows = partitions.iter().map(|partition| partition.rows).sum();
+        self.bytes = partitions.iter().map(|partition| partition.bytes).sum();
+        self.peak_memory = peak_usage();
+        self.partitions = partitions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish() {
+        let mut report = LoadReport::start("binary");
+        let partition = PartitionReport {
+            query: "select 1".to_string(),
+            attempts: 1,
+            rows: 10,
+            bytes: 80,
+            ..PartitionReport::default()
+        };
+        report.finish(Instant::now(), vec![partition.clone(), partition]);
+        assert_eq!(report.rows, 20);
+        assert_eq!(report.bytes, 160);
+        assert_eq!(report.partitions.len(), 2);
+    }
+
+    #[test]
+    fn test_serialize_secs() {
+        let partition = PartitionReport {
+            executed: Duration::from_millis(1500),
+            ..PartitionReport::default()
+        };
+        let json = serde_json::to_value(&partition).unwrap();
+        assert_eq!(json["executed"], 1.5);
+        assert_eq!(json["first_row"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,163 @@
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::cancel::CancelToken;
+
+/// How partitions that fail with a `TransientError` are retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// The number of times a partition is retried, `0` fails the load on the first error.
+    pub retries: u32,
+
+    /// The wait before the first retry, it doubles on every retry up to `max_backoff`.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    /// Partitions are not retried.
+    fn default() -> Self {
+        RetryConfig {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns the wait before the retry number `retry`, starting at 1.
+    fn backoff_of(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_backoff)
+    }
+}
+
+/// An error that might not happen again, e.g. a connection reset or a serialization failure,
+/// so the partition that failed with it can be retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransientError(pub String);
+
+/// Runs `load`, running it again if it fails with a `TransientError` as configured by `retry`.
+/// Returns the result and the number of attempts, panics with the last error if every attempt
+/// failed or if the load was cancelled.
+///
+/// `can_retry` is asked before every retry, e.g. a partition that already wrote some rows to a
+/// file cannot be retried.
+pub fn with_retries<T>(
+    retry: &RetryConfig,
+    cancel_token: &CancelToken,
+    query: &str,
+    can_retry: impl Fn() -> bool,
+    mut load: impl FnMut() -> Result<T, TransientError>,
+) -> (T, u32) {
+    let mut attempt: u32 = 1;
+    loop {
+        let TransientError(message) = match load() {
+            Ok(result) => return (result, attempt),
+            Err(error) => error,
+        };
+
+        cancel_token.check();
+        if attempt > retry.retries || !can_retry() {
+            panic!("{}", message);
+        }
+
+        let backoff = retry.backoff_of(attempt);
+        warn!(
+            "Partition failed on attempt {}, retrying in {:?}: {} | {}",
+            attempt, backoff, message, query
+        );
+        thread::sleep(backoff);
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    fn retry_config(retries: u32) -> RetryConfig {
+        RetryConfig {
+            retries,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_backoff_of() {
+        let retry = retry_config(10);
+        assert_eq!(retry.backoff_of(1), Duration::from_millis(1));
+        assert_eq!(retry.backoff_of(2), Duration::from_millis(2));
+        assert_eq!(retry.backoff_of(3), Duration::from_millis(4));
+        assert_eq!(retry.backoff_of(40), Duration::from_millis(4));
+    }
+
+    #[test]
+    fn test_with_retries() {
+        let attempts = Cell::new(0);
+        let (result, attempt) = with_retries(
+            &retry_config(2),
+            &CancelToken::default(),
+            "select 1",
+            || true,
+            || {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    return Err(TransientError("connection reset".to_string()));
+                }
+                Ok("loaded")
+            },
+        );
+        assert_eq!(result, "loaded");
+        assert_eq!(attempt, 3);
+    }
+
+    #[test]
+    fn test_with_retries_exhausted() {
+        let attempts = Cell::new(0);
+        let panic = panic::catch_unwind(AssertUnwindSafe(|| {
+            with_retries(
+                &retry_config(1),
+                &CancelToken::default(),
+                "select 1",
+                || true,
+                || {
+                    attempts.set(attempts.get() + 1);
+                    Err::<(), _>(TransientError("connection reset".to_string()))
+                },
+            )
+        }))
+        .unwrap_err();
+        assert_eq!(attempts.get(), 2);
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("connection reset")
+        );
+    }
+
+    #[test]
+    fn test_with_retries_cannot_retry() {
+        let attempts = Cell::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            with_retries(
+                &retry_config(3),
+                &CancelToken::default(),
+                "select 1",
+                || false,
+                || {
+                    attempts.set(attempts.get() + 1);
+                    Err::<(), _>(TransientError("connection reset".to_string()))
+                },
+            )
+        }));
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -0,0 +1,142 @@
+//! Scans of a table, the columns and simple filters of a load given as data instead of SQL, so
+//! the source compiles them to its own SQL, see `Source::get_scan_query`. Dataframe libraries
+//! use them to push their projections and predicates down to the database.
+
+use std::str::FromStr;
+
+/// A scan of `table`, only of `columns` if there are any, with the rows that match all of
+/// `filters`, and at most `limit` rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scan {
+    /// The name of the table as written in SQL, e.g. `lineitem` or `public."Line Items"`.
+    pub table: String,
+
+    /// The names of the columns as they are in the result, they are quoted.
+    pub columns: Vec<String>,
+    pub filters: Vec<ScanFilter>,
+    pub limit: Option<u64>,
+}
+
+/// A comparison of a column of a `Scan` with some values, e.g. `l_quantity > 10`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanFilter {
+    pub column: String,
+    pub op: FilterOp,
+    pub values: Vec<ScanValue>,
+}
+
+impl ScanFilter {
+    /// Panics if `values` are not what `op` compares with, see `ScanFilter::try_new`.
+    pub fn new(column: String, op: FilterOp, values: Vec<ScanValue>) -> Self {
+        Self::try_new(column, op, values).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Returns an error if `values` are not what `op` compares with: one value that is not null
+    /// for comparisons, at least one for `In` and `NotIn`, and none for `IsNull` and `IsNotNull`.
+    pub fn try_new(column: String, op: FilterOp, values: Vec<ScanValue>) -> Result<Self, String> {
+        match op {
+            FilterOp::IsNull | FilterOp::IsNotNull if !values.is_empty() => Err(format!(
+                "The filter {op:?} of {column:?} does not take values, got {values:?}"
+            )),
+            FilterOp::In | FilterOp::NotIn if values.is_empty() => Err(format!(
+                "The filter {op:?} of {column:?} needs at least one value"
+            )),
+            FilterOp::IsNull | FilterOp::IsNotNull | FilterOp::In | FilterOp::NotIn => {
+                Ok(ScanFilter { column, op, values })
+            }
+            _ if values.len() != 1 => Err(format!(
+                "The filter {op:?} of {column:?} takes one value, got {values:?}"
+            )),
+            _ if values[0] == ScanValue::Null => Err(format!(
+                "The filter {op:?} of {column:?} cannot compare with null, hint: use 'is null' \
+                or 'is not null'"
+            )),
+            _ => Ok(ScanFilter { column, op, values }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    In,
+    NotIn,
+    IsNull,
+    IsNotNull,
+}
+
+impl FromStr for FilterOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "=" | "==" => Ok(FilterOp::Eq),
+            "!=" | "<>" => Ok(FilterOp::NotEq),
+            "<" => Ok(FilterOp::Lt),
+            "<=" => Ok(FilterOp::LtEq),
+            ">" => Ok(FilterOp::Gt),
+            ">=" => Ok(FilterOp::GtEq),
+            "in" => Ok(FilterOp::In),
+            "not in" => Ok(FilterOp::NotIn),
+            "is null" => Ok(FilterOp::IsNull),
+            "is not null" => Ok(FilterOp::IsNotNull),
+            _ => Err(format!(
+                "The filter operator {s:?} is not valid, options are: '=', '!=', '<', '<=', \
+                '>', '>=', 'in', 'not in', 'is null', 'is not null'"
+            )),
+        }
+    }
+}
+
+/// A value a `ScanFilter` compares with. Other types, e.g. dates, are given as text, the
+/// database casts them to the type of the column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_op_from_str() {
+        assert_eq!("==".parse(), Ok(FilterOp::Eq));
+        assert_eq!("<>".parse(), Ok(FilterOp::NotEq));
+        assert_eq!("NOT IN".parse(), Ok(FilterOp::NotIn));
+        assert_eq!("is not null".parse(), Ok(FilterOp::IsNotNull));
+        assert!("like".parse::<FilterOp>().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot compare with null, hint: use 'is null'")]
+    fn test_filter_compare_with_null() {
+        ScanFilter::new("id".to_string(), FilterOp::Eq, vec![ScanValue::Null]);
+    }
+
+    #[test]
+    fn test_filter_try_new() {
+        let filter = ScanFilter::try_new("id".to_string(), FilterOp::IsNull, vec![]);
+        assert_eq!(filter.map(|filter| filter.op), Ok(FilterOp::IsNull));
+        assert!(
+            ScanFilter::try_new("id".to_string(), FilterOp::Lt, vec![ScanValue::Int(1); 2])
+                .unwrap_err()
+                .contains("takes one value")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "needs at least one value")]
+    fn test_filter_in_without_values() {
+        ScanFilter::new("id".to_string(), FilterOp::In, vec![]);
+    }
+}
@@ -1,6 +1,133 @@
-use arrow::datatypes::{DataType, Field, Schema as ArrowSchema};
+use arrow::array::ArrayRef;
+use arrow::datatypes::{
+    DataType, Field, FieldRef, Fields, Schema as ArrowSchema, DECIMAL128_MAX_PRECISION,
+};
+use log::debug;
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
+/// What to do with a column whose source type cannot be mapped to a `NativeType`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnknownTypes {
+    /// Panic, aborting the whole load. This is the default.
+    Raise,
+
+    /// Fetch the column in text format from the source and load it as `Utf8`, the original
+    /// type is kept in the Arrow field metadata under `conecta.pg_type`.
+    Text,
+}
+
+impl FromStr for UnknownTypes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raise" => Ok(UnknownTypes::Raise),
+            "text" => Ok(UnknownTypes::Text),
+            _ => Err(format!(
+                "unknown_types={:?} is not valid, options are: 'raise', 'text'",
+                s
+            )),
+        }
+    }
+}
+
+/// How geometric types, Postgres' native ones and PostGIS, are returned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOutput {
+    /// Flattened into `List<Float64>`, PostGIS geometries are returned as the EWKB the database
+    /// sends. This is the default.
+    Native,
+
+    /// GeoArrow extension arrays, e.g. `geoarrow.point` or `geoarrow.wkb`.
+    /// See <https://geoarrow.org/extension-types>
+    GeoArrow,
+}
+
+impl FromStr for GeoOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(GeoOutput::Native),
+            "geoarrow" => Ok(GeoOutput::GeoArrow),
+            _ => Err(format!(
+                "geo_output={:?} is not valid, options are: 'native', 'geoarrow'",
+                s
+            )),
+        }
+    }
+}
+
+/// How text columns are encoded when no type is given for them in `schema_overrides`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringEncoding {
+    /// Always `Utf8`. This is the default.
+    Plain,
+
+    /// Picked per column from a sample of the query, see `pick_string_type`.
+    Auto,
+}
+
+impl FromStr for StringEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(StringEncoding::Plain),
+            "auto" => Ok(StringEncoding::Auto),
+            _ => Err(format!(
+                "string_encoding={:?} is not valid, options are: 'plain', 'auto'",
+                s
+            )),
+        }
+    }
+}
+
+/// Maximum ratio of distinct values to rows in the sample for a column to be dictionary encoded.
+pub const DICTIONARY_MAX_DISTINCT_RATIO: f64 = 0.1;
+
+/// Minimum number of sampled rows to consider dictionary encoding, below that the sample
+/// says nothing about the cardinality.
+pub const DICTIONARY_MIN_SAMPLE_ROWS: i64 = 100;
+
+/// Picks the `NativeType` of a text column from a sample of `sample_rows` rows, having
+/// `distinct` different values of `avg_width` bytes on average, out of `estimated_rows`
+/// in the whole result.
+///
+/// * `LargeString` if the estimated size of the column does not fit in `i32` offsets.
+/// * `StringDictionary` if the column has few distinct values, e.g. `l_shipmode` in lineitem.
+/// * `String` otherwise.
+pub fn pick_string_type(
+    sample_rows: i64,
+    distinct: i64,
+    avg_width: f64,
+    estimated_rows: f64,
+) -> NativeType {
+    if avg_width * estimated_rows.max(sample_rows as f64) > i32::MAX as f64 {
+        return NativeType::LargeString;
+    }
+    if sample_rows >= DICTIONARY_MIN_SAMPLE_ROWS
+        && distinct as f64 / sample_rows as f64 <= DICTIONARY_MAX_DISTINCT_RATIO
+    {
+        return NativeType::StringDictionary;
+    }
+    NativeType::String
+}
+
+/// The configuration used to map the source types to `NativeType`.
+#[derive(Debug, Clone)]
+pub struct SchemaConfig {
+    pub unknown_types: UnknownTypes,
+    pub geo_output: GeoOutput,
+    pub string_encoding: StringEncoding,
+
+    /// The `NativeType` of some columns given by the user, by column name, see
+    /// `Schema::apply_overrides`.
+    pub schema_overrides: HashMap<String, NativeType>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum NativeType {
     // Primitive types
@@ -27,14 +154,24 @@ pub enum NativeType {
     F32,
     F64,
 
+    // Decimal, precision and scale.
+    Decimal128(u8, i8),
+
     // String types
     String,
+    LargeString,
+    StringView,
+    // Dictionary encoded `Utf8` with `Int32` keys.
+    StringDictionary,
     UUID,
 
     // Time
     Date32,
     Date64,
     TimestampWithoutTimeZone,
+    TimestampSecond,
+    TimestampMillisecond,
+    TimestampNanosecond,
     Time,
 
     // Vectors
@@ -63,6 +200,21 @@ pub enum NativeType {
     Polygon,
 
     PgGis,
+
+    // GeoArrow, only used with `GeoOutput::GeoArrow`.
+    GeoArrowPoint,
+    GeoArrowLineSegment,
+    GeoArrowPath,
+    GeoArrowBox,
+    GeoArrowPolygon,
+    /// The SRID of the column, if it was declared with one, e.g. `geometry(Point, 4326)`.
+    GeoArrowWkb(Option<i32>),
+
+    // pgvector, the dimension comes from the column's typmod, it is `None` if the column
+    // was declared without one e.g. `vector` instead of `vector(3)`.
+    PgVector(Option<i32>),
+    PgHalfVector(Option<i32>),
+    PgSparseVector,
 }
 
 impl NativeType {
@@ -82,8 +234,15 @@ impl NativeType {
             //Logical
             NativeType::Bool => DataType::Boolean,
 
+            NativeType::Decimal128(precision, scale) => DataType::Decimal128(*precision, *scale),
+
             // Text
             NativeType::String => DataType::Utf8,
+            NativeType::LargeString => DataType::LargeUtf8,
+            NativeType::StringView => DataType::Utf8View,
+            NativeType::StringDictionary => {
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+            }
             NativeType::Bytes => DataType::Binary,
             NativeType::UUID => DataType::FixedSizeBinary(16),
 
@@ -92,6 +251,15 @@ impl NativeType {
             NativeType::TimestampWithoutTimeZone => {
                 DataType::Timestamp(arrow::datatypes::TimeUnit::Microsecond, None)
             }
+            NativeType::TimestampSecond => {
+                DataType::Timestamp(arrow::datatypes::TimeUnit::Second, None)
+            }
+            NativeType::TimestampMillisecond => {
+                DataType::Timestamp(arrow::datatypes::TimeUnit::Millisecond, None)
+            }
+            NativeType::TimestampNanosecond => {
+                DataType::Timestamp(arrow::datatypes::TimeUnit::Nanosecond, None)
+            }
             NativeType::Time => DataType::Time64(arrow::datatypes::TimeUnit::Microsecond),
 
             // Vectors
@@ -134,11 +302,232 @@ impl NativeType {
                 DataType::List(Arc::new(Field::new("_", DataType::Boolean, true)))
             }
             NativeType::PgGis => DataType::Binary,
+            NativeType::PgVector(Some(dim)) => {
+                DataType::FixedSizeList(Arc::new(Field::new("_", DataType::Float32, true)), *dim)
+            }
+            NativeType::PgVector(None) => {
+                DataType::List(Arc::new(Field::new("_", DataType::Float32, true)))
+            }
+            NativeType::PgHalfVector(Some(dim)) => {
+                DataType::FixedSizeList(Arc::new(Field::new("_", DataType::Float16, true)), *dim)
+            }
+            NativeType::PgHalfVector(None) => {
+                DataType::List(Arc::new(Field::new("_", DataType::Float16, true)))
+            }
+            NativeType::PgSparseVector => DataType::Struct(sparse_vector_fields()),
+
+            // GeoArrow
+            NativeType::GeoArrowPoint => DataType::Struct(geoarrow_xy_fields()),
+            NativeType::GeoArrowLineSegment | NativeType::GeoArrowPath => {
+                DataType::List(geoarrow_vertices_field())
+            }
+            NativeType::GeoArrowBox => DataType::Struct(geoarrow_box_fields()),
+            NativeType::GeoArrowPolygon => DataType::List(Arc::new(Field::new(
+                "rings",
+                DataType::List(geoarrow_vertices_field()),
+                false,
+            ))),
+            NativeType::GeoArrowWkb(_) => DataType::Binary,
             _ => {
                 panic!("Native type:: <{:?}> to arrow is not implemented", self)
             }
         }
     }
+
+    /// Returns the Arrow extension type metadata (`ARROW:extension:name` and
+    /// `ARROW:extension:metadata`) of the type, empty if it is not an extension type.
+    pub(crate) fn to_arrow_extension_metadata(&self) -> HashMap<String, String> {
+        let (name, metadata) = match self {
+            NativeType::GeoArrowPoint => ("geoarrow.point", "{}".to_string()),
+            NativeType::GeoArrowLineSegment | NativeType::GeoArrowPath => {
+                ("geoarrow.linestring", "{}".to_string())
+            }
+            NativeType::GeoArrowBox => ("geoarrow.box", "{}".to_string()),
+            NativeType::GeoArrowPolygon => ("geoarrow.polygon", "{}".to_string()),
+            NativeType::GeoArrowWkb(Some(srid)) => (
+                "geoarrow.wkb",
+                format!(r#"{{"crs":"EPSG:{}","crs_type":"authority_code"}}"#, srid),
+            ),
+            NativeType::GeoArrowWkb(None) => ("geoarrow.wkb", "{}".to_string()),
+            _ => return HashMap::new(),
+        };
+        HashMap::from([
+            ("ARROW:extension:name".to_string(), name.to_string()),
+            ("ARROW:extension:metadata".to_string(), metadata),
+        ])
+    }
+}
+
+/// Fields of a GeoArrow coordinate, in the 'separated' struct layout.
+pub(crate) fn geoarrow_xy_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("x", DataType::Float64, false),
+        Field::new("y", DataType::Float64, false),
+    ])
+}
+
+/// Field of the vertices of a GeoArrow linestring or polygon ring.
+pub(crate) fn geoarrow_vertices_field() -> FieldRef {
+    Arc::new(Field::new(
+        "vertices",
+        DataType::Struct(geoarrow_xy_fields()),
+        false,
+    ))
+}
+
+/// Fields of a GeoArrow box.
+pub(crate) fn geoarrow_box_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("xmin", DataType::Float64, false),
+        Field::new("ymin", DataType::Float64, false),
+        Field::new("xmax", DataType::Float64, false),
+        Field::new("ymax", DataType::Float64, false),
+    ])
+}
+
+impl NativeType {
+    /// Returns whether values of this type can be converted to `target` while decoding,
+    /// see `Schema::apply_overrides`.
+    pub fn can_be_overridden_to(&self, target: &NativeType) -> bool {
+        use NativeType::*;
+        matches!(
+            (self, target),
+            (
+                I16 | I32 | I64,
+                I16 | I32 | I64 | F32 | F64 | Decimal128(..)
+            ) | (F32 | F64, F32 | F64 | Decimal128(..))
+                | (String, String | LargeString | StringView | StringDictionary)
+                | (
+                    TimestampWithoutTimeZone,
+                    TimestampSecond
+                        | TimestampMillisecond
+                        | TimestampWithoutTimeZone
+                        | TimestampNanosecond
+                )
+        )
+    }
+}
+
+impl NativeType {
+    /// Returns the `NativeType` of an Arrow `data_type`, the reverse of `to_arrow`. The flattened
+    /// geometric types are read as the list they are, e.g. `Path` as `VecF64`, types without
+    /// a mapping return `None`.
+    pub fn from_arrow(data_type: &DataType) -> Option<NativeType> {
+        use arrow::datatypes::TimeUnit;
+
+        let native_type = match data_type {
+            DataType::Boolean => NativeType::Bool,
+            DataType::Int16 => NativeType::I16,
+            DataType::Int32 => NativeType::I32,
+            DataType::Int64 => NativeType::I64,
+            DataType::Float32 => NativeType::F32,
+            DataType::Float64 => NativeType::F64,
+            DataType::Decimal128(precision, scale) => NativeType::Decimal128(*precision, *scale),
+            DataType::Utf8 => NativeType::String,
+            DataType::LargeUtf8 => NativeType::LargeString,
+            DataType::Utf8View => NativeType::StringView,
+            DataType::Dictionary(key, value)
+                if **key == DataType::Int32 && **value == DataType::Utf8 =>
+            {
+                NativeType::StringDictionary
+            }
+            DataType::Binary => NativeType::Bytes,
+            DataType::FixedSizeBinary(16) => NativeType::UUID,
+            DataType::Date32 => NativeType::Date32,
+            DataType::Time64(TimeUnit::Microsecond) => NativeType::Time,
+            DataType::Timestamp(unit, None) => match unit {
+                TimeUnit::Second => NativeType::TimestampSecond,
+                TimeUnit::Millisecond => NativeType::TimestampMillisecond,
+                TimeUnit::Microsecond => NativeType::TimestampWithoutTimeZone,
+                TimeUnit::Nanosecond => NativeType::TimestampNanosecond,
+            },
+            DataType::List(field) => match field.data_type() {
+                DataType::Boolean => NativeType::VecBool,
+                DataType::Int16 => NativeType::VecI16,
+                DataType::Int32 => NativeType::VecI32,
+                DataType::Int64 => NativeType::VecI64,
+                DataType::Float32 => NativeType::VecF32,
+                DataType::Float64 => NativeType::VecF64,
+                DataType::Utf8 => NativeType::VecString,
+                DataType::Binary => NativeType::VecByte,
+                DataType::FixedSizeBinary(16) => NativeType::VecUUID,
+                _ => return None,
+            },
+            DataType::FixedSizeList(field, dim) => match field.data_type() {
+                DataType::Float32 => NativeType::PgVector(Some(*dim)),
+                DataType::Float16 => NativeType::PgHalfVector(Some(*dim)),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(native_type)
+    }
+}
+
+/// Parses the types that can be given in `schema_overrides`, names follow pyarrow's.
+///
+/// # Example:
+/// ```text
+/// "int64", "large_string", "string_view", "dictionary", "timestamp[ms]", "decimal128(10, 2)"
+/// ```
+impl FromStr for NativeType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let native_type = match s.trim() {
+            "int16" => NativeType::I16,
+            "int32" => NativeType::I32,
+            "int64" => NativeType::I64,
+            "float32" => NativeType::F32,
+            "float64" => NativeType::F64,
+            "string" | "utf8" => NativeType::String,
+            "large_string" | "large_utf8" => NativeType::LargeString,
+            "string_view" | "utf8_view" => NativeType::StringView,
+            "dictionary" | "dictionary<values=string, indices=int32>" => {
+                NativeType::StringDictionary
+            }
+            "timestamp[s]" => NativeType::TimestampSecond,
+            "timestamp[ms]" => NativeType::TimestampMillisecond,
+            "timestamp[us]" => NativeType::TimestampWithoutTimeZone,
+            "timestamp[ns]" => NativeType::TimestampNanosecond,
+            other => {
+                let decimal = other
+                    .strip_prefix("decimal128(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .and_then(|args| args.split_once(','))
+                    .and_then(|(precision, scale)| {
+                        Some((precision.trim().parse().ok()?, scale.trim().parse().ok()?))
+                    });
+                match decimal {
+                    Some((precision, scale))
+                        if !(1..=DECIMAL128_MAX_PRECISION).contains(&precision)
+                            || scale as i16 > precision as i16
+                            || (scale as i16) < -(DECIMAL128_MAX_PRECISION as i16) =>
+                    {
+                        return Err(format!(
+                            "type {s:?} is not valid in schema_overrides, the precision has to \
+                            be from 1 to {DECIMAL128_MAX_PRECISION} and the scale cannot be \
+                            greater than the precision or smaller than \
+                            -{DECIMAL128_MAX_PRECISION}"
+                        ))
+                    }
+                    Some((precision, scale)) => NativeType::Decimal128(precision, scale),
+                    None => return Err(format!("type {:?} is not valid in schema_overrides", s)),
+                }
+            }
+        };
+        Ok(native_type)
+    }
+}
+
+/// Fields of a pgvector `sparsevec`, `dim` is the dimension of the whole vector and `indices`
+/// are the zero-based positions of the non-zero `values`.
+pub(crate) fn sparse_vector_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("dim", DataType::Int32, false),
+        Field::new("indices", DataType::new_list(DataType::Int32, true), false),
+        Field::new("values", DataType::new_list(DataType::Float32, true), false),
+    ])
 }
 
 #[derive(Debug, Clone)]
@@ -150,11 +539,64 @@ impl Schema {
     pub fn to_arrow(self) -> ArrowSchema {
         ArrowSchema::new(
             self.columns
-                .into_iter()
-                .map(|column| Field::new(column.name, column.data_type.to_arrow(), true))
+                .iter()
+                .map(|column| column.to_arrow_field(column.data_type.to_arrow()))
                 .collect::<Vec<_>>(),
         )
     }
+
+    /// Changes the `NativeType` of the columns in `overrides`, values are still decoded as the
+    /// source type and converted to the new one while loading, see `Column::overridden_from`.
+    ///
+    /// Panics if a column does not exist or if its type cannot be converted to the new one.
+    pub fn apply_overrides(&mut self, overrides: &HashMap<String, NativeType>) {
+        for (name, target) in overrides {
+            let column = self
+                .columns
+                .iter_mut()
+                .find(|column| &column.name == name)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "schema_overrides has column {:?} that is not in the query",
+                        name
+                    )
+                });
+
+            // The column may already be converted, e.g. by `StringEncoding::Auto`, the
+            // user's type takes precedence.
+            let source_type = column
+                .overridden_from
+                .take()
+                .unwrap_or_else(|| column.data_type.clone());
+            column.data_type = target.clone();
+
+            if &source_type == target {
+                continue;
+            }
+
+            if !source_type.can_be_overridden_to(target) {
+                panic!(
+                    "schema_overrides: column {:?} of type {:?} cannot be loaded as {:?}",
+                    name, source_type, target
+                )
+            }
+            column.overridden_from = Some(source_type);
+        }
+    }
+
+    /// Marks as nullable the columns that are not nullable in the source but have nulls
+    /// in the loaded `arrays`, which happens for example in the nullable side of an outer join.
+    pub(crate) fn relax_nullability(&mut self, arrays: &[Vec<ArrayRef>]) {
+        for (i, column) in self.columns.iter_mut().enumerate() {
+            if !column.nullable && arrays.iter().any(|chunk| chunk[i].null_count() > 0) {
+                debug!(
+                    "Column {:?} is NOT NULL in the source but has nulls, marking it as nullable",
+                    column.name
+                );
+                column.nullable = true;
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -162,4 +604,188 @@ pub struct Column {
     pub name: String,
     pub data_type: NativeType,
     pub original_type_repr: String,
+
+    /// Whether the column has no `NativeType` mapping and has to be fetched as text,
+    /// see `UnknownTypes::Text`.
+    pub fetch_as_text: bool,
+
+    /// The `NativeType` the column had before being changed by `schema_overrides`, values are
+    /// decoded as this type and then converted to `data_type`.
+    pub overridden_from: Option<NativeType>,
+
+    /// Whether the column can have nulls, e.g. `false` if the column is `NOT NULL` in its table.
+    pub nullable: bool,
+
+    /// Source specific information of the column, like the original type or the table it comes
+    /// from, it is added to the Arrow field metadata. Keys are prefixed with `conecta.`
+    pub metadata: HashMap<String, String>,
+
+    /// The average width in bytes of the values of variable-length columns, like text, used to
+    /// preallocate the data buffers, see `get_arrow_builders`. `None` if it is not known.
+    pub avg_width: Option<usize>,
+}
+
+impl Column {
+    /// Returns the Arrow field of the column with the given `data_type`, with the column's
+    /// nullability and metadata.
+    pub fn to_arrow_field(&self, data_type: DataType) -> Field {
+        let mut metadata = self.data_type.to_arrow_extension_metadata();
+        metadata.extend(self.metadata.clone());
+        Field::new(&self.name, data_type, self.nullable).with_metadata(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int32Array;
+
+    fn column(nullable: bool) -> Column {
+        Column {
+            name: "id".to_string(),
+            data_type: NativeType::I32,
+            original_type_repr: "int4".to_string(),
+            fetch_as_text: false,
+            overridden_from: None,
+            nullable,
+            metadata: HashMap::from([("conecta.table".to_string(), "lineitem".to_string())]),
+            avg_width: None,
+        }
+    }
+
+    #[test]
+    fn test_to_arrow() {
+        let schema = Schema {
+            columns: vec![column(false)],
+        };
+        let field = schema.to_arrow().field(0).clone();
+        assert!(!field.is_nullable());
+        assert_eq!(field.metadata().get("conecta.table").unwrap(), "lineitem");
+    }
+
+    #[test]
+    fn test_relax_nullability() {
+        let mut schema = Schema {
+            columns: vec![column(false)],
+        };
+        let no_nulls: ArrayRef = Arc::new(Int32Array::from(vec![Some(1)]));
+        schema.relax_nullability(&[vec![no_nulls]]);
+        assert!(!schema.columns[0].nullable);
+
+        let nulls: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None]));
+        schema.relax_nullability(&[vec![nulls]]);
+        assert!(schema.columns[0].nullable);
+    }
+
+    #[test]
+    fn test_native_type_from_str() {
+        assert_eq!("int64".parse(), Ok(NativeType::I64));
+        assert_eq!("string_view".parse(), Ok(NativeType::StringView));
+        assert_eq!("dictionary".parse(), Ok(NativeType::StringDictionary));
+        assert_eq!(
+            "timestamp[ms]".parse(),
+            Ok(NativeType::TimestampMillisecond)
+        );
+        assert_eq!(
+            "decimal128(10, 2)".parse(),
+            Ok(NativeType::Decimal128(10, 2))
+        );
+        assert!("decimal128(10)".parse::<NativeType>().is_err());
+        for invalid in [
+            "decimal128(50, 60)",
+            "decimal128(0, 0)",
+            "decimal128(10, 11)",
+            "decimal128(10, -39)",
+        ] {
+            assert!(invalid
+                .parse::<NativeType>()
+                .unwrap_err()
+                .contains("the precision has to be from 1 to 38"));
+        }
+        assert!("uint8".parse::<NativeType>().is_err());
+    }
+
+    #[test]
+    fn test_from_arrow() {
+        for native_type in [
+            NativeType::I64,
+            NativeType::Decimal128(15, 2),
+            NativeType::StringDictionary,
+            NativeType::UUID,
+            NativeType::TimestampMillisecond,
+            NativeType::VecString,
+            NativeType::PgVector(Some(3)),
+        ] {
+            assert_eq!(
+                NativeType::from_arrow(&native_type.to_arrow()),
+                Some(native_type)
+            );
+        }
+        assert_eq!(
+            NativeType::from_arrow(&NativeType::GeoArrowPoint.to_arrow()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut schema = Schema {
+            columns: vec![column(true)],
+        };
+        schema.apply_overrides(&HashMap::from([("id".to_string(), NativeType::I64)]));
+        assert_eq!(schema.columns[0].data_type, NativeType::I64);
+        assert_eq!(schema.columns[0].overridden_from, Some(NativeType::I32));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be loaded as")]
+    fn test_apply_overrides_invalid() {
+        let mut schema = Schema {
+            columns: vec![column(true)],
+        };
+        schema.apply_overrides(&HashMap::from([("id".to_string(), NativeType::StringView)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "not in the query")]
+    fn test_apply_overrides_unknown_column() {
+        let mut schema = Schema {
+            columns: vec![column(true)],
+        };
+        schema.apply_overrides(&HashMap::from([("other".to_string(), NativeType::I64)]));
+    }
+
+    #[test]
+    fn test_apply_overrides_replaces_string_encoding() {
+        let mut string_column = column(true);
+        string_column.data_type = NativeType::StringDictionary;
+        string_column.overridden_from = Some(NativeType::String);
+        let mut schema = Schema {
+            columns: vec![string_column],
+        };
+        schema.apply_overrides(&HashMap::from([("id".to_string(), NativeType::String)]));
+        assert_eq!(schema.columns[0].data_type, NativeType::String);
+        assert_eq!(schema.columns[0].overridden_from, None);
+    }
+
+    #[test]
+    fn test_pick_string_type() {
+        // l_shipmode like, 7 distinct values.
+        assert_eq!(
+            pick_string_type(10_000, 7, 4.0, 6_000_000.0),
+            NativeType::StringDictionary
+        );
+        // l_comment like, mostly unique.
+        assert_eq!(
+            pick_string_type(10_000, 9_800, 27.0, 6_000_000.0),
+            NativeType::String
+        );
+        // Too few rows to tell.
+        assert_eq!(pick_string_type(10, 1, 4.0, 10.0), NativeType::String);
+        // ~4 GiB of text.
+        assert_eq!(
+            pick_string_type(10_000, 10_000, 4096.0, 1_000_000.0),
+            NativeType::LargeString
+        );
+    }
 }
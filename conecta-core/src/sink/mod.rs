@@ -0,0 +1,32 @@
+use std::str::FromStr;
+
+pub mod postgres;
+
+/// What `write_sql` does with the target table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteMode {
+    /// Creates the table, failing if it already exists. This is the default.
+    Create,
+
+    /// Appends the rows to an existing table.
+    Append,
+
+    /// Drops the table if it exists and creates it again.
+    Replace,
+}
+
+impl FromStr for WriteMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(WriteMode::Create),
+            "append" => Ok(WriteMode::Append),
+            "replace" => Ok(WriteMode::Replace),
+            _ => Err(format!(
+                "mode={:?} is not valid, options are: 'create', 'append', 'replace'",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use crate::schema::NativeType;
+use crate::sink::WriteMode;
+use crate::source::postgres::quote_ident;
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::datatypes::{
+    Date32Type, Decimal128Type, Float16Type, Float32Type, Float64Type, Int16Type, Int32Type,
+    Int64Type, Schema, Time64MicrosecondType, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType,
+};
+use arrow::record_batch::RecordBatch;
+use log::debug;
+use postgres::types::Type;
+use postgres::NoTls;
+use r2d2_postgres::postgres;
+use r2d2_postgres::r2d2::Pool;
+use r2d2_postgres::PostgresConnectionManager;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::io::Write;
+
+/// Signature, flags and header extension length of the binary `COPY` format.
+/// See <https://www.postgresql.org/docs/current/sql-copy.html#id-1.9.3.55.9.4>
+const COPY_HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+/// Marks the end of the rows in the binary `COPY` format.
+const COPY_TRAILER: &[u8] = &(-1i16).to_be_bytes();
+
+/// Days between the Unix epoch and the Postgres epoch, 2000-01-01.
+const PG_EPOCH_DAYS: i32 = 10_957;
+
+/// Microseconds between the Unix epoch and the Postgres epoch, 2000-01-01.
+const PG_EPOCH_MICROS: i64 = PG_EPOCH_DAYS as i64 * 86_400 * 1_000_000;
+
+/// Writes Arrow record batches to a Postgres table with `COPY FROM STDIN (FORMAT binary)`.
+#[derive(Debug)]
+pub struct PostgresSink {
+    pub pool: Pool<PostgresConnectionManager<NoTls>>,
+}
+
+impl PostgresSink {
+    /// Writes `batches` to `table`, the batches are split between the connections of the pool
+    /// and every connection runs its own `COPY`, so the write is not atomic.
+    ///
+    /// Returns the number of rows written.
+    pub fn write(&self, table: &str, batches: Vec<RecordBatch>, mode: WriteMode) -> u64 {
+        let Some(schema) = batches.first().map(|batch| batch.schema()) else {
+            return 0;
+        };
+        let native_types = to_native_types(&schema);
+        let table = quote_table(table);
+
+        let mut conn = self.pool.get().expect("Could not get a connection");
+        match mode {
+            WriteMode::Create => {
+                conn.batch_execute(&create_table_query(&table, &schema, &native_types))
+                    .expect("Could not create the table");
+            }
+            WriteMode::Append => {}
+            WriteMode::Replace => {
+                conn.batch_execute(&format!(
+                    "DROP TABLE IF EXISTS {table}; {}",
+                    create_table_query(&table, &schema, &native_types)
+                ))
+                .expect("Could not replace the table");
+            }
+        }
+        drop(conn);
+
+        let copy_query = copy_query(&table, &schema);
+        debug!("{}", copy_query);
+
+        split_batches(batches, self.pool.max_size() as usize)
+            .into_par_iter()
+            .map(|batches| {
+                let mut conn = self.pool.get().expect("Could not get a connection");
+                let mut writer = conn.copy_in(&copy_query).expect("Could not start the COPY");
+                writer.write_all(COPY_HEADER).unwrap();
+
+                let mut buffer: Vec<u8> = Vec::new();
+                for batch in &batches {
+                    encode_batch(batch, &native_types, &mut buffer);
+                    writer
+                        .write_all(&buffer)
+                        .expect("Could not send the rows to COPY");
+                    buffer.clear();
+                }
+
+                writer.write_all(COPY_TRAILER).unwrap();
+                writer.finish().expect("Could not finish the COPY")
+            })
+            .sum()
+    }
+}
+
+/// Returns the `NativeType` of every field of `schema`, panics if one cannot be written.
+fn to_native_types(schema: &Schema) -> Vec<NativeType> {
+    schema
+        .fields()
+        .iter()
+        .map(|field| {
+            NativeType::from_arrow(field.data_type())
+                .filter(|native_type| to_pg_type(native_type).is_some())
+                .unwrap_or_else(|| {
+                    panic!(
+                        "type {} of column {:?} cannot be written to Postgres",
+                        field.data_type(),
+                        field.name()
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Quotes a, maybe schema qualified, table name e.g. `public.lineitem` -> `"public"."lineitem"`.
+fn quote_table(table: &str) -> String {
+    table
+        .split('.')
+        .map(quote_ident)
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+/// Returns the Postgres type a `NativeType` is written as, `None` if it cannot be written.
+fn to_pg_type(native_type: &NativeType) -> Option<String> {
+    let pg_type = match native_type {
+        NativeType::Bool => "boolean",
+        NativeType::I16 => "smallint",
+        NativeType::I32 => "integer",
+        NativeType::I64 => "bigint",
+        NativeType::F32 => "real",
+        NativeType::F64 => "double precision",
+        NativeType::Decimal128(precision, scale) => {
+            return Some(format!("numeric({precision}, {scale})"))
+        }
+        NativeType::String
+        | NativeType::LargeString
+        | NativeType::StringView
+        | NativeType::StringDictionary => "text",
+        NativeType::Bytes => "bytea",
+        NativeType::UUID => "uuid",
+        NativeType::Date32 => "date",
+        NativeType::Time => "time",
+        NativeType::TimestampWithoutTimeZone
+        | NativeType::TimestampSecond
+        | NativeType::TimestampMillisecond
+        | NativeType::TimestampNanosecond => "timestamp",
+        NativeType::VecBool => "boolean[]",
+        NativeType::VecI16 => "smallint[]",
+        NativeType::VecI32 => "integer[]",
+        NativeType::VecI64 => "bigint[]",
+        NativeType::VecF32 => "real[]",
+        NativeType::VecF64 => "double precision[]",
+        NativeType::VecString => "text[]",
+        NativeType::VecByte => "bytea[]",
+        NativeType::VecUUID => "uuid[]",
+        NativeType::PgVector(Some(dim)) => return Some(format!("vector({dim})")),
+        NativeType::PgHalfVector(Some(dim)) => return Some(format!("halfvec({dim})")),
+        _ => return None,
+    };
+    Some(pg_type.to_string())
+}
+
+fn create_table_query(table: &str, schema: &Schema, native_types: &[NativeType]) -> String {
+    let columns: Vec<String> = schema
+        .fields()
+        .iter()
+        .zip(native_types)
+        .map(|(field, native_type)| {
+            format!(
+                "{} {}{}",
+                quote_ident(field.name()),
+                to_pg_type(native_type).unwrap(),
+                if field.is_nullable() { "" } else { " NOT NULL" }
+            )
+        })
+        .collect();
+    format!("CREATE TABLE {table} ({})", columns.join(", "))
+}
+
+fn copy_query(table: &str, schema: &Schema) -> String {
+    let columns: Vec<String> = schema
+        .fields()
+        .iter()
+        .map(|field| quote_ident(field.name()))
+        .collect();
+    format!(
+        "COPY {table} ({}) FROM STDIN (FORMAT binary)",
+        columns.join(", ")
+    )
+}
+
+/// Splits `batches` in at most `parts` groups with about the same number of rows, batches
+/// are sliced, without copying, if needed.
+fn split_batches(batches: Vec<RecordBatch>, parts: usize) -> Vec<Vec<RecordBatch>> {
+    let total_rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+    let rows_per_part = total_rows.div_ceil(parts.max(1)).max(1);
+
+    let mut groups: Vec<Vec<RecordBatch>> = vec![vec![]];
+    let mut rows_in_group = 0;
+    for batch in batches {
+        let mut offset = 0;
+        while offset < batch.num_rows() {
+            if rows_in_group == rows_per_part {
+                groups.push(vec![]);
+                rows_in_group = 0;
+            }
+            let length = (batch.num_rows() - offset).min(rows_per_part - rows_in_group);
+            groups.last_mut().unwrap().push(batch.slice(offset, length));
+            offset += length;
+            rows_in_group += length;
+        }
+    }
+    groups
+}
+
+/// Appends the rows of `batch` to `out` in the binary `COPY` format.
+fn encode_batch(batch: &RecordBatch, native_types: &[NativeType], out: &mut Vec<u8>) {
+    let field_count = batch.num_columns() as i16;
+    for row in 0..batch.num_rows() {
+        out.extend_from_slice(&field_count.to_be_bytes());
+        for (array, native_type) in batch.columns().iter().zip(native_types) {
+            encode_field(array, native_type, row, out);
+        }
+    }
+}
+
+/// Appends a field, the length of the value followed by the value, -1 if it is null.
+fn encode_field(array: &ArrayRef, native_type: &NativeType, row: usize, out: &mut Vec<u8>) {
+    if array.is_null(row) {
+        out.extend_from_slice(&(-1i32).to_be_bytes());
+        return;
+    }
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    encode_value(array, native_type, row, out);
+    let length = (out.len() - start - 4) as i32;
+    out[start..start + 4].copy_from_slice(&length.to_be_bytes());
+}
+
+/// Appends the value at `row` in the binary format of its Postgres type.
+/// See the `*send` functions of Postgres, e.g. `int4send`.
+fn encode_value(array: &ArrayRef, native_type: &NativeType, row: usize, out: &mut Vec<u8>) {
+    match native_type {
+        NativeType::Bool => out.push(array.as_boolean().value(row) as u8),
+        NativeType::I16 => {
+            out.extend_from_slice(&array.as_primitive::<Int16Type>().value(row).to_be_bytes())
+        }
+        NativeType::I32 => {
+            out.extend_from_slice(&array.as_primitive::<Int32Type>().value(row).to_be_bytes())
+        }
+        NativeType::I64 => {
+            out.extend_from_slice(&array.as_primitive::<Int64Type>().value(row).to_be_bytes())
+        }
+        NativeType::F32 => {
+            out.extend_from_slice(&array.as_primitive::<Float32Type>().value(row).to_be_bytes())
+        }
+        NativeType::F64 => {
+            out.extend_from_slice(&array.as_primitive::<Float64Type>().value(row).to_be_bytes())
+        }
+        NativeType::Decimal128(_, scale) => encode_numeric(
+            array.as_primitive::<Decimal128Type>().value(row),
+            *scale,
+            out,
+        ),
+        NativeType::String => out.extend_from_slice(array.as_string::<i32>().value(row).as_bytes()),
+        NativeType::LargeString => {
+            out.extend_from_slice(array.as_string::<i64>().value(row).as_bytes())
+        }
+        NativeType::StringView => {
+            out.extend_from_slice(array.as_string_view().value(row).as_bytes())
+        }
+        NativeType::StringDictionary => {
+            let dictionary = array.as_dictionary::<Int32Type>();
+            let key = dictionary.keys().value(row) as usize;
+            out.extend_from_slice(dictionary.values().as_string::<i32>().value(key).as_bytes())
+        }
+        NativeType::Bytes => out.extend_from_slice(array.as_binary::<i32>().value(row)),
+        NativeType::UUID => out.extend_from_slice(array.as_fixed_size_binary().value(row)),
+        NativeType::Date32 => {
+            let days = array.as_primitive::<Date32Type>().value(row) - PG_EPOCH_DAYS;
+            out.extend_from_slice(&days.to_be_bytes())
+        }
+        NativeType::Time => out.extend_from_slice(
+            &array
+                .as_primitive::<Time64MicrosecondType>()
+                .value(row)
+                .to_be_bytes(),
+        ),
+        NativeType::TimestampWithoutTimeZone
+        | NativeType::TimestampSecond
+        | NativeType::TimestampMillisecond
+        | NativeType::TimestampNanosecond => {
+            let micros = match native_type {
+                NativeType::TimestampSecond => {
+                    array.as_primitive::<TimestampSecondType>().value(row) * 1_000_000
+                }
+                NativeType::TimestampMillisecond => {
+                    array.as_primitive::<TimestampMillisecondType>().value(row) * 1_000
+                }
+                NativeType::TimestampNanosecond => array
+                    .as_primitive::<TimestampNanosecondType>()
+                    .value(row)
+                    .div_euclid(1_000),
+                _ => array.as_primitive::<TimestampMicrosecondType>().value(row),
+            };
+            out.extend_from_slice(&(micros - PG_EPOCH_MICROS).to_be_bytes())
+        }
+        NativeType::VecBool => encode_array(array, row, &NativeType::Bool, Type::BOOL, out),
+        NativeType::VecI16 => encode_array(array, row, &NativeType::I16, Type::INT2, out),
+        NativeType::VecI32 => encode_array(array, row, &NativeType::I32, Type::INT4, out),
+        NativeType::VecI64 => encode_array(array, row, &NativeType::I64, Type::INT8, out),
+        NativeType::VecF32 => encode_array(array, row, &NativeType::F32, Type::FLOAT4, out),
+        NativeType::VecF64 => encode_array(array, row, &NativeType::F64, Type::FLOAT8, out),
+        NativeType::VecString => encode_array(array, row, &NativeType::String, Type::TEXT, out),
+        NativeType::VecByte => encode_array(array, row, &NativeType::Bytes, Type::BYTEA, out),
+        NativeType::VecUUID => encode_array(array, row, &NativeType::UUID, Type::UUID, out),
+        NativeType::PgVector(_) => {
+            let values = array.as_fixed_size_list().value(row);
+            out.extend_from_slice(&(values.len() as u16).to_be_bytes());
+            out.extend_from_slice(&[0; 2]);
+            for value in values.as_primitive::<Float32Type>().values() {
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        NativeType::PgHalfVector(_) => {
+            let values = array.as_fixed_size_list().value(row);
+            out.extend_from_slice(&(values.len() as u16).to_be_bytes());
+            out.extend_from_slice(&[0; 2]);
+            for value in values.as_primitive::<Float16Type>().values() {
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        _ => unreachable!("checked by to_native_types"),
+    }
+}
+
+/// Appends the one-dimensional array in the list at `row`, see `array_send` in Postgres.
+fn encode_array(
+    array: &ArrayRef,
+    row: usize,
+    element: &NativeType,
+    element_type: Type,
+    out: &mut Vec<u8>,
+) {
+    let values = array.as_list::<i32>().value(row);
+    let dimensions: i32 = if values.is_empty() { 0 } else { 1 };
+    out.extend_from_slice(&dimensions.to_be_bytes());
+    out.extend_from_slice(&((values.null_count() > 0) as i32).to_be_bytes());
+    out.extend_from_slice(&element_type.oid().to_be_bytes());
+    if dimensions == 1 {
+        out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+        // Lower bound, Postgres arrays start at 1.
+        out.extend_from_slice(&1i32.to_be_bytes());
+    }
+    for i in 0..values.len() {
+        encode_field(&values, element, i, out);
+    }
+}
+
+/// Appends a decimal, the unscaled `value` with `scale` decimal digits, in the binary format of
+/// `numeric`: base 10000 digits with the weight of the first one. See `numeric_send` in Postgres.
+fn encode_numeric(value: i128, scale: i8, out: &mut Vec<u8>) {
+    // A negative scale means the value is multiplied by 10^-scale.
+    let mut digits = value.unsigned_abs().to_string();
+    let scale = if scale < 0 {
+        digits.push_str(&"0".repeat(scale.unsigned_abs() as usize));
+        0
+    } else {
+        scale as usize
+    };
+    if digits.len() <= scale {
+        digits = format!("{}{}", "0".repeat(scale - digits.len() + 1), digits);
+    }
+    let (integer, fraction) = digits.split_at(digits.len() - scale);
+
+    // Pad so both parts split in groups of 4 digits around the decimal point.
+    let integer = format!("{}{}", "0".repeat((4 - integer.len() % 4) % 4), integer);
+    let fraction = format!("{}{}", fraction, "0".repeat((4 - fraction.len() % 4) % 4));
+    let mut groups: Vec<i16> = integer
+        .as_bytes()
+        .chunks(4)
+        .chain(fraction.as_bytes().chunks(4))
+        .map(|chunk| std::str::from_utf8(chunk).unwrap().parse().unwrap())
+        .collect();
+    let mut weight = (integer.len() / 4) as i16 - 1;
+
+    // Leading and trailing zero groups are not sent.
+    let leading_zeros = groups.iter().take_while(|group| **group == 0).count();
+    groups.drain(..leading_zeros);
+    weight -= leading_zeros as i16;
+    while groups.last() == Some(&0) {
+        groups.pop();
+    }
+    if groups.is_empty() {
+        weight = 0;
+    }
+
+    let sign: u16 = if value < 0 { 0x4000 } else { 0x0000 };
+    out.extend_from_slice(&(groups.len() as i16).to_be_bytes());
+    out.extend_from_slice(&weight.to_be_bytes());
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(scale as u16).to_be_bytes());
+    for group in groups {
+        out.extend_from_slice(&group.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, ListArray, StringArray};
+    use arrow::datatypes::Int32Type;
+    use std::sync::Arc;
+
+    fn numeric(value: i128, scale: i8) -> Vec<u8> {
+        let mut out = vec![];
+        encode_numeric(value, scale, &mut out);
+        out
+    }
+
+    /// The binary `numeric` of the given groups, as sent by Postgres.
+    fn pg_numeric(weight: i16, sign: u16, dscale: u16, groups: &[i16]) -> Vec<u8> {
+        let mut out = vec![];
+        out.extend_from_slice(&(groups.len() as i16).to_be_bytes());
+        out.extend_from_slice(&weight.to_be_bytes());
+        out.extend_from_slice(&sign.to_be_bytes());
+        out.extend_from_slice(&dscale.to_be_bytes());
+        for group in groups {
+            out.extend_from_slice(&group.to_be_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_encode_numeric() {
+        // 12345.678
+        assert_eq!(numeric(12345678, 3), pg_numeric(1, 0, 3, &[1, 2345, 6780]));
+        // -0.05
+        assert_eq!(numeric(-5, 2), pg_numeric(-1, 0x4000, 2, &[500]));
+        // 10000.00
+        assert_eq!(numeric(1000000, 2), pg_numeric(1, 0, 2, &[1]));
+        // 0.00
+        assert_eq!(numeric(0, 2), pg_numeric(0, 0, 2, &[]));
+        // 1200, with scale -2.
+        assert_eq!(numeric(12, -2), pg_numeric(0, 0, 0, &[1200]));
+    }
+
+    #[test]
+    fn test_encode_batch() {
+        let ids: ArrayRef = Arc::new(Int32Array::from(vec![Some(7), None]));
+        let tags: ArrayRef = Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), None]),
+            Some(vec![]),
+        ]));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("tags", tags)]).unwrap();
+        let native_types = to_native_types(&batch.schema());
+        assert_eq!(native_types, vec![NativeType::I32, NativeType::VecI32]);
+
+        let mut out = vec![];
+        encode_batch(&batch, &native_types, &mut out);
+
+        let mut expected: Vec<u8> = vec![];
+        // Row 1: 7, {1, NULL}
+        expected.extend_from_slice(&2i16.to_be_bytes());
+        expected.extend_from_slice(&4i32.to_be_bytes());
+        expected.extend_from_slice(&7i32.to_be_bytes());
+        expected.extend_from_slice(&32i32.to_be_bytes());
+        for header in [1i32, 1, Type::INT4.oid() as i32, 2, 1, 4, 1, -1] {
+            expected.extend_from_slice(&header.to_be_bytes());
+        }
+        // Row 2: NULL, {}
+        expected.extend_from_slice(&2i16.to_be_bytes());
+        expected.extend_from_slice(&(-1i32).to_be_bytes());
+        expected.extend_from_slice(&12i32.to_be_bytes());
+        for header in [0i32, 0, Type::INT4.oid() as i32] {
+            expected.extend_from_slice(&header.to_be_bytes());
+        }
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_split_batches() {
+        let batch = |n: i32| {
+            let array: ArrayRef = Arc::new(Int32Array::from_iter_values(0..n));
+            RecordBatch::try_from_iter([("id", array)]).unwrap()
+        };
+        let groups = split_batches(vec![batch(5), batch(4)], 3);
+        let rows: Vec<usize> = groups
+            .iter()
+            .map(|group| group.iter().map(|b| b.num_rows()).sum())
+            .collect();
+        assert_eq!(rows, vec![3, 3, 3]);
+
+        assert_eq!(split_batches(vec![batch(2)], 8).len(), 2);
+    }
+
+    #[test]
+    fn test_create_table_query() {
+        let names: ArrayRef = Arc::new(StringArray::from(vec!["a"]));
+        let batch = RecordBatch::try_from_iter_with_nullable([("name", names, false)]).unwrap();
+        let schema = batch.schema();
+        assert_eq!(
+            create_table_query(
+                &quote_table("public.users"),
+                &schema,
+                &to_native_types(&schema)
+            ),
+            "CREATE TABLE \"public\".\"users\" (\"name\" text NOT NULL)"
+        );
+        assert_eq!(
+            copy_query("\"users\"", &schema),
+            "COPY \"users\" (\"name\") FROM STDIN (FORMAT binary)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be written to Postgres")]
+    fn test_unsupported_type() {
+        let array: ArrayRef = Arc::new(arrow::array::UInt8Array::from(vec![1]));
+        let batch = RecordBatch::try_from_iter([("id", array)]).unwrap();
+        to_native_types(&batch.schema());
+    }
+}
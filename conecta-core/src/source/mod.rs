@@ -1,4 +1,4 @@
-pub use crate::source::source::Source;
+pub use crate::source::source::{LoadContext, Source};
 use std::fmt::Debug;
 
 pub mod postgres;
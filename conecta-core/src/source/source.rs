@@ -1,24 +1,78 @@
-use crate::metadata::PartitionPlan;
-use crate::schema::Schema;
+use crate::cancel::CancelToken;
+use crate::destination::Destination;
+use crate::metadata::{PartitionExplain, PartitionPlan};
+use crate::progress::Progress;
+use crate::report::PartitionReport;
+use crate::retry::RetryConfig;
+use crate::scan::Scan;
+use crate::schema::{Schema, SchemaConfig};
 use arrow::array::ArrayRef;
 use std::fmt::Debug;
 
+/// What a running load is given besides its plan, shared by all its partitions.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadContext<'a> {
+    /// How partitions that fail with a transient error are retried.
+    pub retry: &'a RetryConfig,
+
+    /// Panics the load when cancelled, the running queries are cancelled.
+    pub cancel_token: &'a CancelToken,
+
+    /// The rows of every partition are added as they are decoded, partitions are indexed in the
+    /// order of the plan.
+    pub progress: &'a Progress,
+}
+
 pub trait Source: Debug + Send + Sync {
-    /// Processes the given partition_plan to an output `schema`
+    /// Processes the given partition_plan to an output `schema`, see `LoadContext` for the
+    /// retries, cancellation and progress of the load.
+    ///
+    /// Returns the report of every partition as well, in the order of the plan.
     fn process_partition_plan(
         &self,
         partition_plan: PartitionPlan,
         schema: Schema,
-    ) -> (Vec<Vec<ArrayRef>>, crate::schema::Schema);
+        context: &LoadContext,
+    ) -> (
+        Vec<Vec<ArrayRef>>,
+        crate::schema::Schema,
+        Vec<PartitionReport>,
+    );
+
+    /// Processes the given partition_plan writing it to `destination` instead of keeping it in
+    /// memory, every partition writes its batches of at most `batch_size` rows as they fill.
+    fn write_partition_plan(
+        &self,
+        partition_plan: PartitionPlan,
+        schema: Schema,
+        destination: &dyn Destination,
+        batch_size: usize,
+        context: &LoadContext,
+    ) -> (Schema, Vec<PartitionReport>);
+
+    /// Returns the number of rows of `query` with its `params` estimated by the database without
+    /// running it, `None` if it cannot be estimated.
+    fn estimate_rows(&self, query: &str, params: &[String]) -> Option<u64>;
+
+    /// Returns the plan the database would run `query` with its `params`, without running it.
+    fn explain(&self, query: &str, params: &[String]) -> PartitionExplain;
+
+    /// Returns how the source transfers rows, see `LoadReport::protocol`.
+    fn protocol(&self) -> &str;
 
     /// Wraps a given SQL query to only give values within the given `bounds`, on the given column.
+    /// The predicate is added to the query itself when that gives the same rows, so the database
+    /// can use an index on the column.
     ///
-    /// The implementation is source dependant as every database might have different syntax.
+    /// The implementation is source dependant as every database might have different syntax, and
+    /// quotes `column` the way the database quotes identifiers.
     ///
     /// # Example:
-    /// ```
-    /// wrap_query_with_bounds("select 1 from tbl", "mycolumn", (1, 10));
-    ///  "select * from (select * from tbl1 ) as t where t.mycolumn > 1 and t.mycolumn < 10"
+    /// ```text
+    /// wrap_query_with_bounds("select * from tbl", "mycolumn", (1, 10), false);
+    ///  "SELECT * FROM tbl WHERE \"mycolumn\" >= 1 AND \"mycolumn\" < 10"
+    /// wrap_query_with_bounds("select * from tbl limit 5", "mycolumn", (1, 10), false);
+    ///  "SELECT * FROM (SELECT * FROM tbl LIMIT 5) AS query_inner WHERE \"mycolumn\" >= 1 AND \"mycolumn\" < 10"
     /// ```
     fn wrap_query_with_bounds(
         &self,
@@ -28,24 +82,76 @@ pub trait Source: Debug + Send + Sync {
         is_last: bool,
     ) -> String;
 
+    /// Wraps a given SQL query to only give the rows whose keys of `partition_on`, several
+    /// columns separated by commas, are from `start`, included, to `stop`, excluded, compared in
+    /// lexicographic order. The last partition has no `stop`.
+    ///
+    /// The keys are parameters of the query after its `params`, returned with them as text, so
+    /// they are never part of the SQL.
+    ///
+    /// # Example:
+    /// ```text
+    /// wrap_query_with_key_bounds("select * from tbl", &[], "a, b", &["1", "5"], Some(&["2", "0"]));
+    ///  ("SELECT * FROM tbl WHERE (\"a\", \"b\") >= ($1, $2) AND (\"a\", \"b\") < ($3, $4)",
+    ///   ["1", "5", "2", "0"])
+    /// ```
+    fn wrap_query_with_key_bounds(
+        &self,
+        query: &str,
+        params: &[String],
+        partition_on: &str,
+        start: &[String],
+        stop: Option<&[String]>,
+    ) -> (String, Vec<String>);
+
+    /// Wraps a given SQL query to only give the rows where a key of `partition_on` is null, the
+    /// rows that are not in any partition of `wrap_query_with_key_bounds`.
+    fn wrap_query_with_null_keys(&self, query: &str, partition_on: &str) -> String;
+
     fn merge_queries(&self, queries: &Vec<String>) -> String;
 
     /// Returns a SQL query that returns the schema of a given query, it can either be
     /// a `LIMIT 0` query or a query to a metadata table, it's source dependant.
     fn get_schema_query(&self, query: &str) -> String;
 
-    /// Returns the name the query's main return table.
-    // TODO: Check if this work on more complex queries like CTEs
-    fn get_table_name(&self, query: &str) -> String;
+    /// Returns the name of every table the query reads, including the ones of joins, set
+    /// operations, subqueries and CTEs, but not the CTEs themselves.
+    fn get_table_names(&self, query: &str) -> Vec<String>;
+
+    /// Fetches the min and max value of the given `column` of the given `query`, run with the
+    /// values of its `params`. They are `None` if `query` has no rows or `column` is always null.
+    fn fetch_min_max(
+        &self,
+        query: &str,
+        params: &[String],
+        column: &str,
+    ) -> (Option<i64>, Option<i64>);
 
-    /// Fetches the min and max value of the given `column` of the given `query`
-    fn fetch_min_max(&self, query: &str, column: &str) -> (Option<i64>, Option<i64>);
+    /// Fetches the first keys of `partition_num` partitions of `query` with as many rows each,
+    /// in the order of the keys of `partition_on`, as text. There are fewer if `query` has fewer
+    /// rows, none if it is empty.
+    fn fetch_key_bounds(
+        &self,
+        query: &str,
+        params: &[String],
+        partition_on: &str,
+        partition_num: u16,
+    ) -> Vec<Vec<String>>;
 
     /// Lets database sources to implement extra validation, most sources
     /// will implement this and do nothing.
     fn validate(&self);
 
-    fn get_schema_of(&self, query: &str) -> Schema;
+    /// Returns the `Schema` of the given query, source types are mapped to `NativeType` as
+    /// configured by `schema_config`.
+    fn get_schema_of(&self, query: &str, params: &[String], schema_config: &SchemaConfig)
+        -> Schema;
 
     fn get_min_max_query(&self, query: &str, col: &str) -> String;
+
+    fn get_key_bounds_query(&self, query: &str, partition_on: &str, partition_num: u16) -> String;
+
+    /// Returns the SQL query of `scan`, in the dialect of the source, with the values of its
+    /// parameters.
+    fn get_scan_query(&self, scan: &Scan) -> (String, Vec<String>);
 }
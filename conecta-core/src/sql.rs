@@ -0,0 +1,1179 @@
+//! Rewrites of the SQL given by the user, done on the `sqlparser` AST instead of formatting
+//! strings, so trailing semicolons, comments, `ORDER BY ... LIMIT`, `WITH` or `TABLE foo`
+//! queries can be wrapped.
+//!
+//! Queries are parsed with `parse_query`, which only accepts one statement that reads data,
+//! the rewritten queries are rendered back to SQL with their `Display`.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    BinaryOperator, CastKind, DataType, ExactNumberInfo, Expr, GroupByExpr, Ident, LimitClause,
+    ObjectNamePart, Query, Select, SelectItem, SetExpr, Statement, TableFactor, Value, Visit,
+    VisitMut, Visitor, VisitorMut, WildcardAdditionalOptions,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::scan::{FilterOp, Scan, ScanFilter, ScanValue};
+
+/// The derived table of the templates of `wrap_query`, replaced by the wrapped query.
+pub(crate) const INNER_QUERY: &str = "(select 1) as query_inner";
+
+/// Parses `sql` as one query that only reads data.
+///
+/// Panics if it cannot be parsed, has several statements, is not a query, e.g. `INSERT` or
+/// `CREATE TABLE`, or modifies data, e.g. a `WITH` of a `DELETE` or a `SELECT INTO`.
+pub fn parse_query(sql: &str) -> Query {
+    let statements = Parser::parse_sql(&PostgreSqlDialect {}, &expand_table(sql))
+        .unwrap_or_else(|e| panic!("Could not parse the query {sql:?}: {e}"));
+    let [statement] = <[Statement; 1]>::try_from(statements).unwrap_or_else(|statements| {
+        panic!(
+            "Expected one query but got {} statements, pass every query on its own",
+            statements.len()
+        )
+    });
+    let Statement::Query(query) = statement else {
+        panic!("Only queries that read data can be loaded, got: {statement}")
+    };
+    check_read_only(&query);
+    *query
+}
+
+/// Replaces every `TABLE tbl` query of `sql` with the `SELECT * FROM tbl` it stands for,
+/// sqlparser does not parse it as a statement or within parentheses, e.g. after `wrap_query`.
+fn expand_table(sql: &str) -> String {
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, sql).tokenize_with_location() else {
+        // The parser gives a better error.
+        return sql.to_string();
+    };
+
+    // `TABLE` starts a query at the start of a statement, a parenthesis or a set operation.
+    let mut tables = vec![];
+    let mut previous: Option<&Token> = None;
+    for token in &tokens {
+        if let Token::Whitespace(_) = token.token {
+            continue;
+        }
+        let starts_query = match previous {
+            None | Some(Token::LParen | Token::SemiColon) => true,
+            Some(Token::Word(word)) => matches!(
+                word.keyword,
+                Keyword::UNION | Keyword::INTERSECT | Keyword::EXCEPT | Keyword::ALL
+            ),
+            _ => false,
+        };
+        if let Token::Word(word) = &token.token {
+            if starts_query && word.keyword == Keyword::TABLE && word.quote_style.is_none() {
+                tables.push(token.span.start);
+            }
+        }
+        previous = Some(&token.token);
+    }
+
+    let mut expanded = sql.to_string();
+    for start in tables.iter().rev() {
+        let line_start = sql
+            .split_inclusive('\n')
+            .take(start.line as usize - 1)
+            .map(str::len)
+            .sum::<usize>();
+        let offset = sql[line_start..]
+            .char_indices()
+            .nth(start.column as usize - 1)
+            .map_or(sql.len(), |(i, _)| line_start + i);
+        expanded.replace_range(offset..offset + "TABLE".len(), "SELECT * FROM");
+    }
+    expanded
+}
+
+fn check_read_only(query: &Query) {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            check_read_only(&cte.query);
+        }
+    }
+    check_set_expr_read_only(&query.body);
+}
+
+fn check_set_expr_read_only(body: &SetExpr) {
+    match body {
+        SetExpr::Select(select) => {
+            if select.into.is_some() {
+                panic!("SELECT INTO creates a table, only queries that read data can be loaded")
+            }
+        }
+        SetExpr::Query(query) => check_read_only(query),
+        SetExpr::SetOperation { left, right, .. } => {
+            check_set_expr_read_only(left);
+            check_set_expr_read_only(right);
+        }
+        SetExpr::Insert(statement)
+        | SetExpr::Update(statement)
+        | SetExpr::Delete(statement)
+        | SetExpr::Merge(statement) => {
+            panic!("Only queries that read data can be loaded, got: {statement}")
+        }
+        SetExpr::Values(_) | SetExpr::Table(_) => {}
+    }
+}
+
+/// Parses an expression, all of `sql` has to be the expression.
+fn try_parse_expr(sql: &str) -> Result<Expr, ParserError> {
+    let mut parser = Parser::new(&PostgreSqlDialect {}).try_with_sql(sql)?;
+    let expr = parser.parse_expr()?;
+    parser.expect_token(&Token::EOF)?;
+    Ok(expr)
+}
+
+fn parse_expr(sql: &str) -> Expr {
+    try_parse_expr(sql).unwrap_or_else(|e| panic!("Could not parse the expression {sql:?}: {e}"))
+}
+
+/// Parses `partition_on`, the keys to partition on separated by commas, e.g. `tenant_id, id`.
+/// Every key is a column of the query, e.g. `id` or `"Order Id"`, or an expression of its
+/// columns, e.g. `id % 1000` or `extract(epoch from created_at)`.
+///
+/// Columns are quoted, with unquoted names in lowercase like Postgres folds them, so they can
+/// be put in the queries of the partitions even if they are keywords, e.g. `"select"`.
+///
+/// Panics if a key is not a column or an expression of columns, e.g. if it has a subquery.
+pub fn parse_partition_on(partition_on: &str) -> Vec<Expr> {
+    let mut keys = try_parse_exprs(partition_on).unwrap_or_else(|e| {
+        panic!(
+            "partition_on has to be a column of the query or an expression of its columns, got \
+            {partition_on:?}: {e}, hint: quote names that are not valid identifiers, e.g. \
+            '\"Order Id\"'"
+        )
+    });
+
+    for key in &mut keys {
+        let mut columns = PartitionOnColumns::default();
+        if let ControlFlow::Break(reason) = VisitMut::visit(key, &mut columns) {
+            panic!("partition_on {reason}, got {partition_on:?}")
+        }
+        if !columns.found {
+            panic!("partition_on has to use a column of the query, got {partition_on:?}")
+        }
+    }
+    keys
+}
+
+/// Parses `partition_on` as one key, see `parse_partition_on`.
+fn parse_partition_key(partition_on: &str) -> Expr {
+    let [key] = <[Expr; 1]>::try_from(parse_partition_on(partition_on)).unwrap_or_else(|keys| {
+        panic!(
+            "Expected one partition_on key but got {}, {partition_on:?}",
+            keys.len()
+        )
+    });
+    key
+}
+
+fn try_parse_exprs(sql: &str) -> Result<Vec<Expr>, ParserError> {
+    let mut parser = Parser::new(&PostgreSqlDialect {}).try_with_sql(sql)?;
+    let exprs = parser.parse_comma_separated(Parser::parse_expr)?;
+    parser.expect_token(&Token::EOF)?;
+    Ok(exprs)
+}
+
+/// The `VisitorMut` of `parse_partition_on`, it quotes the columns of the expression and breaks
+/// with the reason if it is not allowed.
+#[derive(Default)]
+struct PartitionOnColumns {
+    found: bool,
+}
+
+impl VisitorMut for PartitionOnColumns {
+    type Break = &'static str;
+
+    fn pre_visit_query(&mut self, _query: &mut Query) -> ControlFlow<Self::Break> {
+        ControlFlow::Break("cannot have subqueries, only the columns of the query")
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Identifier(ident) => {
+                if ident.quote_style.is_none() {
+                    ident.value.make_ascii_lowercase();
+                }
+                ident.quote_style = Some('"');
+                self.found = true;
+            }
+            Expr::CompoundIdentifier(_) => {
+                return ControlFlow::Break(
+                    "has to use the columns of the query without their table",
+                )
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Returns `column` in parentheses if it is an expression, rows already are.
+fn nested(column: &Expr) -> Expr {
+    match column {
+        Expr::Identifier(_) | Expr::Tuple(_) => column.clone(),
+        _ => Expr::Nested(Box::new(column.clone())),
+    }
+}
+
+/// Returns `column op value`.
+fn compare(column: &Expr, op: BinaryOperator, value: Expr) -> Expr {
+    Expr::BinaryOp {
+        left: Box::new(nested(column)),
+        op,
+        right: Box::new(value),
+    }
+}
+
+/// The `SELECT` of the body of `query`, it has to be one.
+fn select_mut(query: &mut Query) -> &mut Select {
+    match query.body.as_mut() {
+        SetExpr::Select(select) => select,
+        _ => unreachable!("the query is not a select"),
+    }
+}
+
+/// Returns the query `outer`, whose first table is `INNER_QUERY`, with `query` instead.
+///
+/// # Example:
+/// ```text
+/// wrap_query("select * from tbl;", "select count(*) from (select 1) as query_inner")
+///   -> SELECT count(*) FROM (SELECT * FROM tbl) AS query_inner
+/// ```
+pub fn wrap_query(query: &str, outer: &str) -> Query {
+    wrap_parsed_query(parse_query(query), outer)
+}
+
+fn wrap_parsed_query(query: Query, outer: &str) -> Query {
+    debug_assert!(outer.contains(INNER_QUERY));
+    let mut outer = parse_query(outer);
+    match &mut select_mut(&mut outer).from[0].relation {
+        TableFactor::Derived { subquery, .. } => **subquery = query,
+        _ => unreachable!("the first table of the outer query is not {INNER_QUERY}"),
+    }
+    outer
+}
+
+/// Returns `query` with only the rows where `column` is within `bounds`, the last partition
+/// includes the upper bound. `column` can be an expression, see `parse_partition_on`, it is only
+/// used in the predicate so the columns of `query` stay the same.
+///
+/// The predicate is added to the `WHERE` of `query` if it only selects columns as they are from
+/// a single table, e.g. `select * from tbl`, so the database can use an index on `column`,
+/// otherwise `query` is wrapped with the predicate, e.g. if it has a `LIMIT` or a `GROUP BY`.
+pub fn wrap_query_with_bounds(
+    query: &str,
+    column: &str,
+    bounds: (i64, i64),
+    is_last: bool,
+) -> String {
+    let column = parse_partition_key(column);
+    let upper = if is_last {
+        BinaryOperator::LtEq
+    } else {
+        BinaryOperator::Lt
+    };
+    let predicate = Expr::BinaryOp {
+        left: Box::new(compare(
+            &column,
+            BinaryOperator::GtEq,
+            parse_expr(&bounds.0.to_string()),
+        )),
+        op: BinaryOperator::And,
+        right: Box::new(compare(&column, upper, parse_expr(&bounds.1.to_string()))),
+    };
+    filter_query(query, predicate)
+}
+
+/// Returns `query` with only the rows whose keys of `partition_on` are from `start`, included,
+/// to `stop`, excluded, compared as rows, i.e. in lexicographic order, the last partition has
+/// no `stop`. The keys are parameters of the query after the `params` it already has, `start`
+/// first, which are returned with them. They are given as text, Postgres parses them as the type
+/// of their key.
+///
+/// The predicate is added to the `WHERE` of `query` like in `wrap_query_with_bounds`, so the
+/// database can use an index on the keys.
+///
+/// # Example:
+/// ```text
+/// wrap_query_with_key_bounds("select * from tbl", &[], "tenant_id, id", &["1", "500"], Some(&["2", "0"]))
+///   -> SELECT * FROM tbl WHERE ("tenant_id", "id") >= ($1, $2) AND ("tenant_id", "id") < ($3, $4)
+///      with the parameters ["1", "500", "2", "0"]
+/// ```
+pub fn wrap_query_with_key_bounds(
+    query: &str,
+    params: &[String],
+    partition_on: &str,
+    start: &[String],
+    stop: Option<&[String]>,
+) -> (String, Vec<String>) {
+    let keys = Expr::Tuple(parse_partition_on(partition_on));
+    let mut params = params.to_vec();
+    let mut row = |values: &[String]| {
+        Expr::Tuple(
+            values
+                .iter()
+                .map(|value| {
+                    params.push(value.clone());
+                    Expr::value(Value::Placeholder(format!("${}", params.len())))
+                })
+                .collect(),
+        )
+    };
+
+    let mut predicate = compare(&keys, BinaryOperator::GtEq, row(start));
+    if let Some(stop) = stop {
+        predicate = Expr::BinaryOp {
+            left: Box::new(predicate),
+            op: BinaryOperator::And,
+            right: Box::new(compare(&keys, BinaryOperator::Lt, row(stop))),
+        };
+    }
+    (filter_query(query, predicate), params)
+}
+
+/// Returns `query` with only the rows where a key of `partition_on` is null, which no range of
+/// keys of `wrap_query_with_key_bounds` has.
+///
+/// # Example:
+/// ```text
+/// wrap_query_with_null_keys("select * from tbl", "tenant_id, id % 10")
+///   -> SELECT * FROM tbl WHERE ("tenant_id" IS NULL OR ("id" % 10) IS NULL)
+/// ```
+pub fn wrap_query_with_null_keys(query: &str, partition_on: &str) -> String {
+    // Nested, it can be added to the `WHERE` of `query` with an `AND`.
+    let predicate = parse_partition_on(partition_on)
+        .iter()
+        .map(|key| Expr::IsNull(Box::new(nested(key))))
+        .reduce(|left, right| Expr::BinaryOp {
+            left: Box::new(left),
+            op: BinaryOperator::Or,
+            right: Box::new(right),
+        })
+        .expect("partition_on has at least one key");
+    filter_query(query, Expr::Nested(Box::new(predicate)))
+}
+
+/// Returns `query` with only the rows that match `predicate`, which is added to the `WHERE` of
+/// `query` if `can_push_down`, otherwise `query` is wrapped with it.
+fn filter_query(query: &str, predicate: Expr) -> String {
+    let mut query = parse_query(query);
+    if can_push_down(&query) {
+        let select = select_mut(&mut query);
+        select.selection = Some(match select.selection.take() {
+            Some(selection) => Expr::BinaryOp {
+                left: Box::new(Expr::Nested(Box::new(selection))),
+                op: BinaryOperator::And,
+                right: Box::new(predicate),
+            },
+            None => predicate,
+        });
+        return query.to_string();
+    }
+
+    let mut outer = wrap_parsed_query(query, &format!("select * from {INNER_QUERY}"));
+    select_mut(&mut outer).selection = Some(predicate);
+    outer.to_string()
+}
+
+/// Compares identifiers like Postgres, unquoted identifiers are case-insensitive.
+fn same_ident(a: &Ident, b: &Ident) -> bool {
+    match (a.quote_style, b.quote_style) {
+        (None, None) => a.value.eq_ignore_ascii_case(&b.value),
+        (None, Some(_)) => a.value.to_lowercase() == b.value,
+        (Some(_), None) => a.value == b.value.to_lowercase(),
+        (Some(_), Some(_)) => a.value == b.value,
+    }
+}
+
+/// Whether a predicate on the columns of `query` can be added to its `WHERE` without changing
+/// the rows it returns: `query` is a plain `SELECT` of one table that only selects columns
+/// unchanged, without anything that is applied after `WHERE` and depends on the rows it
+/// filters, like `LIMIT`, `DISTINCT`, `GROUP BY` or window functions.
+fn can_push_down(query: &Query) -> bool {
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return false;
+    };
+    let plain_query = query.limit_clause.is_none()
+        && query.fetch.is_none()
+        && query.for_clause.is_none()
+        && query.settings.is_none()
+        && query.format_clause.is_none()
+        && query.pipe_operators.is_empty();
+    let plain_select = select.distinct.is_none()
+        && select.top.is_none()
+        && select.exclude.is_none()
+        && select.into.is_none()
+        && select.lateral_views.is_empty()
+        && select.prewhere.is_none()
+        && matches!(
+            &select.group_by,
+            GroupByExpr::Expressions(exprs, modifiers) if exprs.is_empty() && modifiers.is_empty()
+        )
+        && select.cluster_by.is_empty()
+        && select.distribute_by.is_empty()
+        && select.sort_by.is_empty()
+        && select.having.is_none()
+        && select.named_window.is_empty()
+        && select.qualify.is_none()
+        && select.value_table_mode.is_none()
+        && select.connect_by.is_none();
+    let one_table = matches!(
+        select.from.as_slice(),
+        [table] if table.joins.is_empty() && matches!(table.relation, TableFactor::Table { .. })
+    );
+    if !(plain_query && plain_select && one_table) {
+        return false;
+    }
+
+    // Only columns can be selected as they are, an expression or an alias could be named like a
+    // column of `column`, which the `WHERE` does not see. A column of `column` that is not
+    // selected can only be the one of the table, e.g. in `select name from tbl`.
+    select.projection.iter().all(|item| {
+        matches!(
+            item,
+            SelectItem::Wildcard(_)
+                | SelectItem::QualifiedWildcard(..)
+                | SelectItem::UnnamedExpr(Expr::Identifier(_) | Expr::CompoundIdentifier(_))
+        )
+    })
+}
+
+/// Returns the query that counts the rows of all `queries` together.
+pub fn merge_queries(queries: &[String]) -> String {
+    let counts = queries.iter().map(|query| {
+        Expr::Subquery(Box::new(wrap_query(
+            query,
+            &format!("select count(*) from {INNER_QUERY}"),
+        )))
+    });
+    let sum = counts
+        .reduce(|left, right| Expr::BinaryOp {
+            left: Box::new(left),
+            op: BinaryOperator::Plus,
+            right: Box::new(right),
+        })
+        .expect("must pass some queries!");
+
+    let mut merged = parse_query("select 1");
+    select_mut(&mut merged).projection = vec![SelectItem::UnnamedExpr(sum)];
+    merged.to_string()
+}
+
+/// Returns the query that returns no rows of `query`, only its columns.
+pub fn get_schema_query(query: &str) -> String {
+    wrap_query(query, &format!("select * from {INNER_QUERY} limit 0")).to_string()
+}
+
+/// Returns the query that fetches the min and max of `column` of `query`, as `bigint`.
+///
+/// If `column` is an expression they are rounded down and up, expressions like
+/// `extract(epoch from created_at)` are not integers and the bounds have to include them.
+pub fn get_min_max_query(query: &str, column: &str) -> String {
+    let query = with_table_columns(query);
+    let outer = match parse_partition_key(column) {
+        column @ Expr::Identifier(_) => {
+            format!("select min({column})::bigint, max({column})::bigint from {INNER_QUERY}")
+        }
+        expr => format!(
+            "select floor(min({expr}))::bigint, ceil(max({expr}))::bigint from {INNER_QUERY}"
+        ),
+    };
+    wrap_parsed_query(query, &outer).to_string()
+}
+
+/// Returns `query` selecting all the columns of its table if `can_push_down`, so the partition
+/// metadata is fetched with its `partition_on` columns that it does not select, like the
+/// partitions do, e.g. `select name from tbl` becomes `select * from tbl`. Its rows stay the
+/// same.
+fn with_table_columns(query: &str) -> Query {
+    let mut query = parse_query(query);
+    if can_push_down(&query) {
+        query.order_by = None;
+        select_mut(&mut query).projection =
+            vec![SelectItem::Wildcard(WildcardAdditionalOptions::default())];
+    }
+    query
+}
+
+/// Returns the query that fetches the first keys of `partition_num` partitions of `query` with
+/// as many rows each, in the order of the keys of `partition_on`. Keys are returned as text,
+/// rows where a key is null are not counted.
+///
+/// Every row of `query` is sorted, which an index on the keys avoids.
+pub fn get_key_bounds_query(query: &str, partition_on: &str, partition_num: u16) -> String {
+    let keys = parse_partition_on(partition_on);
+    let list = |items: Vec<String>| items.join(", ");
+    let aliased = (0..keys.len())
+        .map(|i| format!("{} as key_{i}", keys[i]))
+        .collect();
+    let not_null: Vec<String> = keys
+        .iter()
+        .map(|key| format!("{} is not null", nested(key)))
+        .collect();
+
+    let buckets = wrap_parsed_query(
+        with_table_columns(query),
+        &format!(
+            "select {}, ntile({partition_num}) over (order by {}) as bucket from {INNER_QUERY} \
+            where {}",
+            list(aliased),
+            list(keys.iter().map(Expr::to_string).collect()),
+            not_null.join(" and "),
+        ),
+    );
+    // The keys are sorted by their values, not as the text of the columns of the result.
+    parse_query(&format!(
+        "select distinct on (bucket) {} from ({buckets}) as buckets order by bucket, {}",
+        list((0..keys.len()).map(|i| format!("key_{i}::text")).collect()),
+        list(
+            (0..keys.len())
+                .map(|i| format!("buckets.key_{i}"))
+                .collect()
+        ),
+    ))
+    .to_string()
+}
+
+/// Returns the query of `scan`, its columns are quoted and the values of its filters are the
+/// `$1`, `$2`, ... parameters of the query, which are returned with it as text, so they are never
+/// part of the SQL.
+///
+/// Panics if the table of `scan` is not the name of a table.
+pub fn scan_query(scan: &Scan) -> (String, Vec<String>) {
+    let table = Parser::new(&PostgreSqlDialect {})
+        .try_with_sql(&scan.table)
+        .and_then(|mut parser| {
+            let name = parser.parse_object_name(false)?;
+            parser.expect_token(&Token::EOF)?;
+            Ok(name)
+        })
+        .unwrap_or_else(|e| {
+            panic!(
+                "The table of a scan has to be the name of a table, e.g. 'lineitem' or \
+                'public.\"Line Items\"', got {:?}: {e}",
+                scan.table
+            )
+        });
+
+    let mut query = parse_query(&format!("select * from {table}"));
+    let select = select_mut(&mut query);
+    if !scan.columns.is_empty() {
+        select.projection = scan
+            .columns
+            .iter()
+            .map(|column| SelectItem::UnnamedExpr(Expr::Identifier(Ident::with_quote('"', column))))
+            .collect();
+    }
+    let mut params = vec![];
+    select.selection = scan
+        .filters
+        .iter()
+        .map(|filter| filter_expr(filter, &mut params))
+        .reduce(|left, right| Expr::BinaryOp {
+            left: Box::new(left),
+            op: BinaryOperator::And,
+            right: Box::new(right),
+        });
+    query.limit_clause = scan.limit.map(|limit| LimitClause::LimitOffset {
+        limit: Some(Expr::value(Value::Number(limit.to_string(), false))),
+        offset: None,
+        limit_by: vec![],
+    });
+    (query.to_string(), params)
+}
+
+/// Returns the predicate of `filter`, its values are pushed to `params`. They are cast to the
+/// type their literal would have, e.g. `1.5` is a `NUMERIC` also for an integer column, text is
+/// parsed as the type of the column.
+fn filter_expr(filter: &ScanFilter, params: &mut Vec<String>) -> Expr {
+    let column = Box::new(Expr::Identifier(Ident::with_quote('"', &filter.column)));
+    let mut values = filter.values.iter().map(|value| {
+        let (param, data_type) = match value {
+            ScanValue::Null => return Expr::value(Value::Null),
+            ScanValue::Bool(value) => (value.to_string(), Some(DataType::Boolean)),
+            ScanValue::Int(value) => (value.to_string(), Some(DataType::BigInt(None))),
+            ScanValue::Float(value) if value.is_finite() => (
+                value.to_string(),
+                Some(DataType::Numeric(ExactNumberInfo::None)),
+            ),
+            // Postgres reads 'NaN', 'inf' and '-inf' as floats, they are no numeric literals.
+            ScanValue::Float(value) => (value.to_string(), None),
+            ScanValue::Text(value) => (value.clone(), None),
+        };
+        params.push(param);
+        let placeholder = Expr::value(Value::Placeholder(format!("${}", params.len())));
+        match data_type {
+            Some(data_type) => Expr::Cast {
+                kind: CastKind::DoubleColon,
+                expr: Box::new(placeholder),
+                data_type,
+                format: None,
+            },
+            None => placeholder,
+        }
+    });
+
+    let op = match filter.op {
+        FilterOp::Eq => BinaryOperator::Eq,
+        FilterOp::NotEq => BinaryOperator::NotEq,
+        FilterOp::Lt => BinaryOperator::Lt,
+        FilterOp::LtEq => BinaryOperator::LtEq,
+        FilterOp::Gt => BinaryOperator::Gt,
+        FilterOp::GtEq => BinaryOperator::GtEq,
+        FilterOp::In | FilterOp::NotIn => {
+            return Expr::InList {
+                expr: column,
+                list: values.collect(),
+                negated: filter.op == FilterOp::NotIn,
+            }
+        }
+        FilterOp::IsNull => return Expr::IsNull(column),
+        FilterOp::IsNotNull => return Expr::IsNotNull(column),
+    };
+    Expr::BinaryOp {
+        left: column,
+        op,
+        right: Box::new(values.next_back().expect("a comparison has one value")),
+    }
+}
+
+/// Returns every table `query` reads, in joins, set operations, subqueries or CTEs, in the
+/// order they appear. The CTEs themselves are not tables, a name is only a CTE after its
+/// definition, e.g. `with orders as (select * from orders) ...` reads the table `orders`.
+///
+/// Names are as written in the query, schema-qualified or quoted if they are.
+pub fn get_table_names(query: &str) -> Vec<String> {
+    let mut table_names = TableNames::default();
+    let _ = parse_query(query).visit(&mut table_names);
+    table_names.names
+}
+
+/// The CTEs a query and the queries within it can read instead of a table.
+struct Scope {
+    ctes: Vec<Ident>,
+
+    /// The CTEs that the query of every CTE of the query can read: the ones defined before it,
+    /// and itself if the `WITH` is recursive. Queries are compared by address, as `Visitor` is
+    /// given them without a lifetime.
+    cte_queries: Vec<(*const Query, Vec<Ident>)>,
+}
+
+/// The `Visitor` of `get_table_names`.
+#[derive(Default)]
+struct TableNames {
+    scopes: Vec<Scope>,
+    names: Vec<String>,
+}
+
+impl Visitor for TableNames {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        let ctes = match self.scopes.last() {
+            Some(scope) => scope
+                .cte_queries
+                .iter()
+                .find(|(cte_query, _)| std::ptr::eq(*cte_query, query))
+                .map_or(&scope.ctes, |(_, ctes)| ctes)
+                .clone(),
+            None => vec![],
+        };
+
+        let mut scope = Scope {
+            ctes,
+            cte_queries: vec![],
+        };
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                let mut cte_ctes = scope.ctes.clone();
+                if with.recursive {
+                    cte_ctes.push(cte.alias.name.clone());
+                }
+                scope.cte_queries.push((&*cte.query, cte_ctes));
+                scope.ctes.push(cte.alias.name.clone());
+            }
+        }
+        self.scopes.push(scope);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<()> {
+        self.scopes.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<()> {
+        // Tables with arguments are functions, e.g. `generate_series(1, 10)`.
+        let TableFactor::Table {
+            name, args: None, ..
+        } = table_factor
+        else {
+            return ControlFlow::Continue(());
+        };
+
+        let is_cte = match name.0.as_slice() {
+            [ObjectNamePart::Identifier(ident)] => self
+                .scopes
+                .last()
+                .is_some_and(|scope| scope.ctes.iter().any(|cte| same_ident(cte, ident))),
+            _ => false,
+        };
+        let name = name.to_string();
+        if !is_cte && !self.names.contains(&name) {
+            self.names.push(name);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        // Semicolons and comments are not part of the query.
+        assert_eq!(
+            parse_query("select * from tbl; -- all of it").to_string(),
+            "SELECT * FROM tbl"
+        );
+        assert_eq!(
+            parse_query("table \"My\".tbl;").to_string(),
+            "SELECT * FROM \"My\".tbl"
+        );
+    }
+
+    #[test]
+    fn test_expand_table() {
+        assert_eq!(
+            expand_table("table \"Tbl\";\n(Table tbl) union all table \"table\""),
+            "SELECT * FROM \"Tbl\";\n(SELECT * FROM tbl) union all SELECT * FROM \"table\""
+        );
+        assert_eq!(
+            expand_table("select 'ñ' as tableau from table(f())"),
+            "select 'ñ' as tableau from table(f())"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected one query but got 2 statements")]
+    fn test_parse_query_several_statements() {
+        parse_query("select 1; drop table tbl");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected one query but got 2 statements")]
+    fn test_parse_query_several_statements_table() {
+        parse_query("table tbl; drop table tbl");
+    }
+
+    #[test]
+    #[should_panic(expected = "Only queries that read data can be loaded, got: DELETE FROM tbl")]
+    fn test_parse_query_dml() {
+        parse_query("delete from tbl");
+    }
+
+    #[test]
+    #[should_panic(expected = "Only queries that read data can be loaded")]
+    fn test_parse_query_dml_cte() {
+        parse_query("with deleted as (delete from tbl returning *) select * from deleted");
+    }
+
+    #[test]
+    #[should_panic(expected = "SELECT INTO creates a table")]
+    fn test_parse_query_select_into() {
+        parse_query("select * into copy from tbl");
+    }
+
+    #[test]
+    fn test_wrap_query_with_bounds_pushed_down() {
+        assert_eq!(
+            wrap_query_with_bounds("select * from tbl;", "id", (0, 10), false),
+            "SELECT * FROM tbl WHERE \"id\" >= 0 AND \"id\" < 10"
+        );
+        assert_eq!(
+            wrap_query_with_bounds(
+                "select t.id, name from tbl as t where a = 1 or b = 2 order by id",
+                "id",
+                (0, 10),
+                true
+            ),
+            "SELECT t.id, name FROM tbl AS t WHERE (a = 1 OR b = 2) AND \"id\" >= 0 \
+             AND \"id\" <= 10 ORDER BY id"
+        );
+        // `id` is not selected, so it is the column of the table.
+        assert_eq!(
+            wrap_query_with_bounds("select name from tbl", "id", (0, 10), false),
+            "SELECT name FROM tbl WHERE \"id\" >= 0 AND \"id\" < 10"
+        );
+    }
+
+    #[test]
+    fn test_wrap_query_with_bounds_wrapped() {
+        let wrapped = |query: &str| wrap_query_with_bounds(query, "id", (0, 10), false);
+        assert_eq!(
+            wrapped("select * from tbl order by id limit 5"),
+            "SELECT * FROM (SELECT * FROM tbl ORDER BY id LIMIT 5) AS query_inner \
+             WHERE \"id\" >= 0 AND \"id\" < 10"
+        );
+        assert_eq!(
+            wrapped("with t as (select 1 as id limit 1) select id from t union table t"),
+            "SELECT * FROM (WITH t AS (SELECT 1 AS id LIMIT 1) \
+             SELECT id FROM t UNION SELECT * FROM t) AS query_inner \
+             WHERE \"id\" >= 0 AND \"id\" < 10"
+        );
+
+        // Not safe to push down.
+        for query in [
+            "select other as id from tbl",
+            "select id + 1 as id from tbl",
+            "select distinct id from tbl",
+            "select id from tbl group by id",
+            "select id, row_number() over () from tbl",
+            "select * from a join b using (id)",
+            "select 1 as a union all select 2",
+        ] {
+            assert!(wrapped(query).ends_with(") AS query_inner WHERE \"id\" >= 0 AND \"id\" < 10"));
+        }
+    }
+
+    #[test]
+    fn test_wrap_query_with_bounds_quoted() {
+        assert_eq!(
+            wrap_query_with_bounds(
+                "select \"Order Id\", \"select\" from tbl",
+                "\"Order Id\"",
+                (-10, 10),
+                true
+            ),
+            "SELECT \"Order Id\", \"select\" FROM tbl \
+             WHERE \"Order Id\" >= -10 AND \"Order Id\" <= 10"
+        );
+        assert_eq!(
+            get_min_max_query("select * from tbl", "\"select\""),
+            "SELECT min(\"select\")::BIGINT, max(\"select\")::BIGINT \
+             FROM (SELECT * FROM tbl) AS query_inner"
+        );
+    }
+
+    #[test]
+    fn test_parse_partition_on() {
+        for (partition_on, expected) in [
+            ("L_OrderKey", "\"l_orderkey\""),
+            ("\"Order \"\"Id\"\"\"", "\"Order \"\"Id\"\"\""),
+            ("select", "\"select\""),
+            ("Id % 1000", "\"id\" % 1000"),
+            ("extract(epoch from ts)", "EXTRACT(EPOCH FROM \"ts\")"),
+            ("hashtext(Tenant)", "hashtext(\"tenant\")"),
+        ] {
+            assert_eq!(parse_partition_key(partition_on).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_partition_on_not_a_column() {
+        for partition_on in [
+            "Order Id",
+            "id; drop table tbl",
+            "id) from tbl; drop table tbl; --",
+            "",
+            "1000",
+            "now()",
+            "id + (select max(id) from tbl)",
+            "array(select id from tbl)",
+        ] {
+            let result = std::panic::catch_unwind(|| parse_partition_key(partition_on));
+            assert!(result.is_err(), "{partition_on:?} was accepted");
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "partition_on has to use the columns of the query without their table"
+    )]
+    fn test_parse_partition_on_table() {
+        parse_partition_on("t.id % 10");
+    }
+
+    #[test]
+    fn test_parse_partition_on_keys() {
+        let keys = parse_partition_on("Tenant_Id, id % 10");
+        assert_eq!(
+            keys.iter().map(|key| key.to_string()).collect::<Vec<_>>(),
+            ["\"tenant_id\"", "\"id\" % 10"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected one partition_on key but got 2")]
+    fn test_wrap_query_with_bounds_keys() {
+        wrap_query_with_bounds("select * from tbl", "tenant_id, id", (0, 10), false);
+    }
+
+    #[test]
+    fn test_wrap_query_with_key_bounds() {
+        let start = ["1".to_string(), "it's".to_string()];
+        let stop = ["2".to_string(), "a".to_string()];
+        assert_eq!(
+            wrap_query_with_key_bounds(
+                "select * from tbl where x = 1",
+                &[],
+                "tenant_id, name",
+                &start,
+                Some(&stop)
+            ),
+            (
+                "SELECT * FROM tbl WHERE (x = 1) AND (\"tenant_id\", \"name\") >= ($1, $2) \
+                 AND (\"tenant_id\", \"name\") < ($3, $4)"
+                    .to_string(),
+                vec![
+                    "1".to_string(),
+                    "it's".to_string(),
+                    "2".to_string(),
+                    "a".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            wrap_query_with_key_bounds(
+                "select tenant_id from tbl",
+                &[],
+                "tenant_id, name",
+                &start,
+                None
+            ),
+            (
+                "SELECT tenant_id FROM tbl WHERE (\"tenant_id\", \"name\") >= ($1, $2)".to_string(),
+                start.to_vec()
+            )
+        );
+        // The keys are numbered after the parameters the query already has.
+        assert_eq!(
+            wrap_query_with_key_bounds(
+                "select * from tbl where x = $1",
+                &["it's".to_string()],
+                "id",
+                &["1".to_string()],
+                None
+            ),
+            (
+                "SELECT * FROM tbl WHERE (x = $1) AND (\"id\") >= ($2)".to_string(),
+                vec!["it's".to_string(), "1".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_wrap_query_with_null_keys() {
+        assert_eq!(
+            wrap_query_with_null_keys("select * from tbl where x = 1", "tenant_id, id % 10"),
+            "SELECT * FROM tbl WHERE (x = 1) AND (\"tenant_id\" IS NULL OR (\"id\" % 10) IS NULL)"
+        );
+        // `tenant` could be the alias, so it is only in the outer query.
+        assert_eq!(
+            wrap_query_with_null_keys("select upper(name) as tenant, id from tbl", "tenant, id"),
+            "SELECT * FROM (SELECT upper(name) AS tenant, id FROM tbl) AS query_inner \
+             WHERE (\"tenant\" IS NULL OR \"id\" IS NULL)"
+        );
+    }
+
+    #[test]
+    fn test_scan_query() {
+        let filter = |column: &str, op: FilterOp, values: Vec<ScanValue>| {
+            ScanFilter::new(column.to_string(), op, values)
+        };
+        let scan = Scan {
+            table: "public.\"Line Items\"".to_string(),
+            columns: vec!["l_orderkey".to_string(), "Order Id".to_string()],
+            filters: vec![
+                filter("l_quantity", FilterOp::GtEq, vec![ScanValue::Float(1.5)]),
+                filter(
+                    "l_shipmode",
+                    FilterOp::NotEq,
+                    vec![ScanValue::Text("it's".to_string())],
+                ),
+                filter(
+                    "l_linenumber",
+                    FilterOp::In,
+                    vec![ScanValue::Int(-1), ScanValue::Int(2)],
+                ),
+                filter("l_returnflag", FilterOp::IsNull, vec![]),
+                filter("l_paid", FilterOp::Eq, vec![ScanValue::Bool(true)]),
+                filter("l_tax", FilterOp::Lt, vec![ScanValue::Float(f64::INFINITY)]),
+            ],
+            limit: Some(10),
+        };
+        assert_eq!(
+            scan_query(&scan),
+            (
+                "SELECT \"l_orderkey\", \"Order Id\" FROM public.\"Line Items\" \
+                 WHERE \"l_quantity\" >= $1::NUMERIC AND \"l_shipmode\" <> $2 \
+                 AND \"l_linenumber\" IN ($3::BIGINT, $4::BIGINT) AND \"l_returnflag\" IS NULL \
+                 AND \"l_paid\" = $5::BOOLEAN AND \"l_tax\" < $6 LIMIT 10"
+                    .to_string(),
+                ["1.5", "it's", "-1", "2", "true", "inf"]
+                    .map(str::to_string)
+                    .to_vec()
+            )
+        );
+
+        let scan = Scan {
+            table: "lineitem".to_string(),
+            columns: vec![],
+            filters: vec![],
+            limit: None,
+        };
+        assert_eq!(
+            scan_query(&scan),
+            ("SELECT * FROM lineitem".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The table of a scan has to be the name of a table")]
+    fn test_scan_query_not_a_table() {
+        scan_query(&Scan {
+            table: "lineitem; drop table lineitem".to_string(),
+            columns: vec![],
+            filters: vec![],
+            limit: None,
+        });
+    }
+
+    #[test]
+    fn test_get_key_bounds_query() {
+        assert_eq!(
+            get_key_bounds_query("select * from tbl;", "tenant_id, id % 10", 4),
+            "SELECT DISTINCT ON (bucket) key_0::TEXT, key_1::TEXT FROM (\
+             SELECT \"tenant_id\" AS key_0, \"id\" % 10 AS key_1, \
+             ntile(4) OVER (ORDER BY \"tenant_id\", \"id\" % 10) AS bucket \
+             FROM (SELECT * FROM tbl) AS query_inner \
+             WHERE \"tenant_id\" IS NOT NULL AND (\"id\" % 10) IS NOT NULL) AS buckets \
+             ORDER BY bucket, buckets.key_0, buckets.key_1"
+        );
+    }
+
+    #[test]
+    fn test_wrap_query_with_bounds_expression() {
+        assert_eq!(
+            wrap_query_with_bounds("select * from tbl", "id % 1000", (0, 500), false),
+            "SELECT * FROM tbl WHERE (\"id\" % 1000) >= 0 AND (\"id\" % 1000) < 500"
+        );
+        // `tenant` could be the alias, so it is only in the outer query.
+        assert_eq!(
+            wrap_query_with_bounds(
+                "select upper(name) as tenant from tbl",
+                "hashtext(tenant)",
+                (-5, 5),
+                true
+            ),
+            "SELECT * FROM (SELECT upper(name) AS tenant FROM tbl) AS query_inner \
+             WHERE (hashtext(\"tenant\")) >= -5 AND (hashtext(\"tenant\")) <= 5"
+        );
+        assert_eq!(
+            get_min_max_query("select * from tbl", "extract(epoch from ts)"),
+            "SELECT FLOOR(min(EXTRACT(EPOCH FROM \"ts\")))::BIGINT, \
+             CEIL(max(EXTRACT(EPOCH FROM \"ts\")))::BIGINT FROM (SELECT * FROM tbl) AS query_inner"
+        );
+    }
+
+    #[test]
+    fn test_same_ident() {
+        assert!(same_ident(&Ident::new("ID"), &Ident::new("id")));
+        assert!(same_ident(&Ident::new("Id"), &Ident::with_quote('"', "id")));
+        assert!(!same_ident(
+            &Ident::new("id"),
+            &Ident::with_quote('"', "Id")
+        ));
+    }
+
+    #[test]
+    fn test_merge_queries() {
+        assert_eq!(
+            merge_queries(&["select * from a;".to_string(), "table b".to_string()]),
+            "SELECT (SELECT count(*) FROM (SELECT * FROM a) AS query_inner) + \
+             (SELECT count(*) FROM (SELECT * FROM b) AS query_inner)"
+        );
+    }
+
+    #[test]
+    fn test_get_schema_query() {
+        assert_eq!(
+            get_schema_query("select * from tbl -- comment"),
+            "SELECT * FROM (SELECT * FROM tbl) AS query_inner LIMIT 0"
+        );
+    }
+
+    #[test]
+    fn test_get_min_max_query() {
+        assert_eq!(
+            get_min_max_query("select * from tbl order by id limit 10;", "id"),
+            "SELECT min(\"id\")::BIGINT, max(\"id\")::BIGINT \
+             FROM (SELECT * FROM tbl ORDER BY id LIMIT 10) AS query_inner"
+        );
+        // The partitions filter the table, so `id` does not need to be selected.
+        assert_eq!(
+            get_min_max_query("select name from tbl where name <> '' order by 1", "id"),
+            "SELECT min(\"id\")::BIGINT, max(\"id\")::BIGINT \
+             FROM (SELECT * FROM tbl WHERE name <> '') AS query_inner"
+        );
+    }
+
+    #[test]
+    fn test_get_table_names() {
+        assert_eq!(get_table_names("select * from tbl"), vec!["tbl"]);
+        assert_eq!(
+            get_table_names(
+                "select * from public.orders as o \
+                 join \"Order Items\" i on o.id = i.order_id \
+                 left join lateral (select * from discounts d where d.id = o.id) as d on true \
+                 where exists (select 1 from returns r where r.order_id = o.id) \
+                 union all table archive.orders"
+            ),
+            vec![
+                "public.orders",
+                "\"Order Items\"",
+                "discounts",
+                "returns",
+                "archive.orders"
+            ]
+        );
+        assert_eq!(
+            get_table_names("select * from generate_series(1, 10) as s, tbl, tbl"),
+            vec!["tbl"]
+        );
+    }
+
+    #[test]
+    fn test_get_table_names_ctes() {
+        assert_eq!(
+            get_table_names(
+                "with orders as (select * from orders where total > 0), \
+                 recent as (select * from orders where ts > now() - interval '1 day') \
+                 select * from recent join customers using (customer_id) \
+                 where customer_id in (with orders as (select 1) select * from orders)"
+            ),
+            vec!["orders", "customers"]
+        );
+        // A CTE is not visible outside of the query it is defined in.
+        assert_eq!(
+            get_table_names("select * from (with t as (select * from a) select * from t) as s, t"),
+            vec!["a", "t"]
+        );
+        // Recursive CTEs read themselves.
+        assert_eq!(
+            get_table_names(
+                "with recursive tree as (select * from nodes \
+                 union all select n.* from nodes n join tree on n.parent = tree.id) \
+                 select * from tree"
+            ),
+            vec!["nodes"]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Async loads on `tokio-postgres`, behind the `async` feature, see `read_sql_async`.
+//!
+//! Partitions run as concurrent tasks on the tokio runtime of the caller, every partition
+//! fetches its rows in batches of `batch_size` and decodes them on the blocking pool while it
+//! fetches the next one. The partition plan and the schema are created with the metadata
+//! queries of `Connection`, also on the blocking pool.
+
+use std::any::Any;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use deadpool_postgres::{Manager, Pool};
+use futures::future::{self, Either};
+use futures::{pin_mut, Stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio::task::{JoinError, JoinHandle};
+use tokio_postgres::NoTls;
+
+use crate::partition::PartitionConfig;
+use crate::schema::{Schema, SchemaConfig};
+use crate::source::postgres::{decode_rows, text_params, wrap_query_with_text_casts};
+use crate::{connect, default_pool_size, make_record_batch};
+
+/// The error of an async load, e.g. a failed query or a panic while decoding its rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadError(pub String);
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Returns a failed query as a `LoadError`, with the message of the database if it has one.
+fn query_error(context: &str, error: tokio_postgres::Error) -> LoadError {
+    match error.as_db_error() {
+        Some(db_error) => LoadError(format!("{}: {}", context, db_error)),
+        None => LoadError(format!("{}: {}", context, error)),
+    }
+}
+
+/// The message of a panic, as given to `panic!`.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+/// Returns the panic of a task of the blocking pool as a `LoadError`.
+fn join_error(error: JoinError) -> LoadError {
+    match error.try_into_panic() {
+        Ok(payload) => LoadError(panic_message(payload)),
+        Err(error) => LoadError(error.to_string()),
+    }
+}
+
+/// Runs `f` on the blocking pool, a panic is returned as a `LoadError`.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, LoadError> {
+    tokio::task::spawn_blocking(f).await.map_err(join_error)
+}
+
+/// The record batches of an async load, in the order the partitions load them.
+///
+/// The load stops when the stream is dropped, cancelling its queries in the database. A
+/// partition that fails sends its error and stops, the others keep loading.
+pub struct RecordBatchStream {
+    schema: SchemaRef,
+    batches: mpsc::Receiver<Result<RecordBatch, LoadError>>,
+}
+
+impl RecordBatchStream {
+    /// The schema of the batches, known before any is loaded. All its columns are nullable,
+    /// like in `export_sql`.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for RecordBatchStream {
+    type Item = Result<RecordBatch, LoadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.batches.poll_recv(cx)
+    }
+}
+
+/// Loads the queries of `partition_config` like `conecta_core::read_sql`, but asynchronously,
+/// streaming record batches of at most `batch_size` rows as the partitions load them. See `stream`.
+///
+/// It has to be called within a tokio runtime, errors of the metadata queries are returned,
+/// errors of the partitions are sent in the stream.
+pub async fn read_sql_async(
+    connection_string: &str,
+    partition_config: PartitionConfig,
+    schema_config: SchemaConfig,
+    max_pool_size: Option<u32>,
+    batch_size: usize,
+) -> Result<RecordBatchStream, LoadError> {
+    assert!(batch_size > 0, "batch_size has to be greater than 0");
+    let max_pool_size = max_pool_size.unwrap_or_else(|| default_pool_size(&partition_config));
+
+    let metadata_connection_string = connection_string.to_string();
+    let (partition_plan, mut schema) = blocking(move || {
+        let connection = connect(&metadata_connection_string, 1);
+        let first_query = partition_config.query.first().unwrap().clone();
+        let params = partition_config.params.clone();
+        let partition_plan = connection.plan(partition_config, false);
+        let schema = connection.schema(&first_query, &params, &schema_config);
+        (partition_plan, schema)
+    })
+    .await?;
+    for column in schema.columns.iter_mut() {
+        column.nullable = true;
+    }
+
+    let config: tokio_postgres::Config = connection_string
+        .parse()
+        .map_err(|e| LoadError(format!("Invalid connection string: {}", e)))?;
+    let pool = Pool::builder(Manager::new(config, NoTls))
+        .max_size(max_pool_size as usize)
+        .build()
+        .map_err(|e| LoadError(format!("Could not create a pool of connections: {}", e)))?;
+
+    // Partitions wait for the stream to be read if it has a batch of every partition.
+    let (sender, batches) = mpsc::channel(partition_plan.data_queries.len().max(1));
+    let arrow_schema = Arc::new(schema.clone().to_arrow());
+    let schema = Arc::new(schema);
+    for (query, params) in partition_plan
+        .data_queries
+        .into_iter()
+        .zip(partition_plan.data_params)
+    {
+        let pool = pool.clone();
+        let schema = schema.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                load_partition(&pool, &query, &params, &schema, batch_size, &sender).await
+            {
+                let _ = sender.send(Err(e)).await;
+            }
+        });
+    }
+
+    Ok(RecordBatchStream {
+        schema: arrow_schema,
+        batches,
+    })
+}
+
+/// Loads the partition `query` with its `params` and sends its record batches to `sender`, if
+/// the stream is dropped its query is cancelled in the database.
+async fn load_partition(
+    pool: &Pool,
+    query: &str,
+    params: &[String],
+    schema: &Arc<Schema>,
+    batch_size: usize,
+    sender: &mpsc::Sender<Result<RecordBatch, LoadError>>,
+) -> Result<(), LoadError> {
+    let client = pool
+        .get()
+        .await
+        .map_err(|e| LoadError(format!("Could not get a connection: {}", e)))?;
+
+    let load = fetch_partition(&client, query, params, schema, batch_size, sender);
+    let closed = sender.closed();
+    pin_mut!(load, closed);
+    match future::select(load, closed).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => {
+            let _ = client.cancel_token().cancel_query(NoTls).await;
+            Ok(())
+        }
+    }
+}
+
+/// Fetches the rows of `query` with its `params` in batches of `batch_size` rows and sends them
+/// to `sender`.
+async fn fetch_partition(
+    client: &tokio_postgres::Client,
+    query: &str,
+    params: &[String],
+    schema: &Arc<Schema>,
+    batch_size: usize,
+    sender: &mpsc::Sender<Result<RecordBatch, LoadError>>,
+) -> Result<(), LoadError> {
+    let query = wrap_query_with_text_casts(query, schema);
+    let rows = client
+        .query_raw(query.as_str(), text_params(params))
+        .await
+        .map_err(|e| query_error("Query failed", e))?;
+    pin_mut!(rows);
+
+    // The batch that is being decoded while the next one is fetched.
+    let mut decoding: Option<JoinHandle<RecordBatch>> = None;
+    let mut chunk = Vec::with_capacity(batch_size);
+    while let Some(row) = rows.next().await {
+        chunk.push(row.map_err(|e| query_error("Query failed", e))?);
+        if chunk.len() == batch_size {
+            let full = std::mem::replace(&mut chunk, Vec::with_capacity(batch_size));
+            if !send_decoded(decoding.replace(decode(full, schema)), sender).await? {
+                return Ok(());
+            }
+        }
+    }
+    let last = (!chunk.is_empty()).then(|| decode(chunk, schema));
+    if send_decoded(decoding, sender).await? {
+        send_decoded(last, sender).await?;
+    }
+    Ok(())
+}
+
+/// Decodes `rows` into a record batch on the blocking pool.
+fn decode(rows: Vec<tokio_postgres::Row>, schema: &Arc<Schema>) -> JoinHandle<RecordBatch> {
+    let schema = schema.clone();
+    tokio::task::spawn_blocking(move || make_record_batch(decode_rows(&rows, &schema), &schema))
+}
+
+/// Sends the batch that is `decoding`, if any, to `sender` once it is decoded. Returns `false`
+/// if the stream was dropped.
+async fn send_decoded(
+    decoding: Option<JoinHandle<RecordBatch>>,
+    sender: &mpsc::Sender<Result<RecordBatch, LoadError>>,
+) -> Result<bool, LoadError> {
+    let Some(decoding) = decoding else {
+        return Ok(true);
+    };
+    let batch = decoding.await.map_err(join_error)?;
+    Ok(sender.send(Ok(batch)).await.is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_blocking() {
+        assert_eq!(block_on(blocking(|| 1)), Ok(1));
+        assert_eq!(
+            block_on(blocking(|| -> i32 { panic!("Could not decode") })),
+            Err(LoadError("Could not decode".to_string()))
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! `tracing` spans of the phases of a load, behind the `tracing` feature.
+//!
+//! Loads emit these spans, all at the `INFO` level:
+//!
+//! * `conecta.load`: the whole `read_sql` or `export_sql`, with its `rows` and `bytes`.
+//! * `conecta.plan`: creating the partition plan.
+//! * `conecta.metadata`: every metadata query, e.g. the min/max of `partition_on` or the schema.
+//! * `conecta.partition`: the data query of every partition and the decoding of its rows, with
+//!   its `rows`, `bytes` and `attempts`.
+//!
+//! The SQL of the spans is redacted with `redact_sql`. Without the feature `span!` creates a
+//! `Span` that does nothing, its arguments are not evaluated.
+
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+#[cfg(feature = "tracing")]
+pub use tracing;
+#[cfg(feature = "tracing")]
+pub use tracing::field::Empty;
+#[cfg(feature = "tracing")]
+pub use tracing::Span;
+
+/// Creates an `INFO` span, it takes the same arguments as `tracing::info_span!`.
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! span {
+    ($($args:tt)*) => {
+        $crate::trace::tracing::info_span!($($args)*)
+    };
+}
+
+/// Creates an `INFO` span, it takes the same arguments as `tracing::info_span!`.
+#[cfg(not(feature = "tracing"))]
+#[macro_export]
+macro_rules! span {
+    ($($args:tt)*) => {
+        $crate::trace::Span
+    };
+}
+
+/// A span that does nothing, used without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    pub fn current() -> Self {
+        Span
+    }
+
+    pub fn record<V>(&self, _field: &str, _value: V) -> &Self {
+        self
+    }
+
+    pub fn entered(self) -> Self {
+        self
+    }
+
+    pub fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+}
+
+/// Returns `query` with its literals replaced by `?` and without its comments, so it can be
+/// sent to a tracing backend.
+///
+/// # Example:
+/// ```text
+/// "select * from t where name = 'secret' and id > 10" -> "select * from t where name = ? and id > ?"
+/// ```
+pub fn redact_sql(query: &str) -> String {
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, query).tokenize() else {
+        // It cannot be told apart what is a literal.
+        return "?".to_string();
+    };
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::Number(..)
+            | Token::SingleQuotedString(_)
+            | Token::TripleSingleQuotedString(_)
+            | Token::DollarQuotedString(_)
+            | Token::SingleQuotedByteStringLiteral(_)
+            | Token::DoubleQuotedByteStringLiteral(_)
+            | Token::TripleSingleQuotedByteStringLiteral(_)
+            | Token::TripleDoubleQuotedByteStringLiteral(_)
+            | Token::SingleQuotedRawStringLiteral(_)
+            | Token::DoubleQuotedRawStringLiteral(_)
+            | Token::TripleSingleQuotedRawStringLiteral(_)
+            | Token::TripleDoubleQuotedRawStringLiteral(_)
+            | Token::NationalStringLiteral(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::UnicodeStringLiteral(_)
+            | Token::HexStringLiteral(_) => "?".to_string(),
+            Token::Whitespace(
+                Whitespace::SingleLineComment { .. } | Whitespace::MultiLineComment(_),
+            ) => " ".to_string(),
+            token => token.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sql() {
+        assert_eq!(
+            redact_sql("select * from t where name = 'secret' and id > 10"),
+            "select * from t where name = ? and id > ?"
+        );
+        assert_eq!(
+            redact_sql("select \"id\", $$secret$$ -- secret\nfrom t /* secret */"),
+            "select \"id\", ?  from t  "
+        );
+        assert_eq!(redact_sql("select 'unterminated"), "?");
+    }
+
+    #[test]
+    fn test_span() {
+        let span = span!("conecta.test", rows = Empty);
+        span.record("rows", 10);
+        let _entered = span.entered();
+    }
+}
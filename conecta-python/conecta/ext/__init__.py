@@ -1,4 +1,5 @@
 import functools
+import json
 import re
 
 import polars
@@ -53,6 +54,149 @@ def _read_sql_conecta(
     return from_arrow(tbl, schema_overrides=schema_overrides)
 
 
+_COMPARISONS = {'Eq': '=', 'NotEq': '!=', 'Lt': '<', 'LtEq': '<=', 'Gt': '>', 'GtEq': '>='}
+_FLIPPED = {'=': '=', '!=': '!=', '<': '>', '<=': '>=', '>': '<', '>=': '<='}
+_NUMBER = re.compile(r'U?Int\d*|Float\d*')
+
+
+def _literal(node) -> tuple | None:
+    """
+    Returns a tuple with the value of a polars literal if it is a number, a string or a bool,
+    literals of other types, e.g. dates, are not pushed down.
+    """
+    if not isinstance(node, dict) or list(node) != ['Literal']:
+        return None
+    literal = node['Literal']
+    if isinstance(literal, dict) and 'Dyn' in literal:
+        literal = literal['Dyn']
+    elif isinstance(literal, dict) and 'Scalar' in literal:
+        dtype = literal['Scalar'].get('dtype')
+        if not (isinstance(dtype, str)
+                and (_NUMBER.fullmatch(dtype) or dtype in ('String', 'Boolean'))):
+            return None
+        literal = literal['Scalar'].get('value')
+    if not isinstance(literal, dict) or len(literal) != 1:
+        return None
+    (kind, value), = literal.items()
+    if isinstance(value, bool):
+        return (value,) if kind == 'Boolean' else None
+    if isinstance(value, (int, float)) and _NUMBER.fullmatch(kind):
+        return (value,)
+    if isinstance(value, str) and kind in ('Str', 'String'):
+        return (value,)
+    return None
+
+
+def _column(node) -> str | None:
+    if isinstance(node, dict) and list(node) == ['Column'] and isinstance(node['Column'], str):
+        return node['Column']
+    return None
+
+
+def _scan_filters(node) -> tuple[list, bool]:
+    """
+    Translates a serialized polars predicate to the filters of a ``conecta.Scan``, the parts
+    of an AND that are not a comparison of a column with a literal or a null check are
+    skipped. Returns the filters and whether they are the whole predicate.
+    """
+    if not isinstance(node, dict) or len(node) != 1:
+        return [], False
+
+    if 'BinaryExpr' in node:
+        expr = node['BinaryExpr']
+        op = expr.get('op')
+        if op in ('And', 'LogicalAnd'):
+            left, left_complete = _scan_filters(expr.get('left'))
+            right, right_complete = _scan_filters(expr.get('right'))
+            return left + right, left_complete and right_complete
+        if op not in _COMPARISONS:
+            return [], False
+        op = _COMPARISONS[op]
+        column, value = _column(expr.get('left')), _literal(expr.get('right'))
+        if column is None:
+            column, value = _column(expr.get('right')), _literal(expr.get('left'))
+            op = _FLIPPED[op]
+        if column is None or value is None:
+            return [], False
+        # Strings are ordered by the collation of the database, which polars does not know.
+        if isinstance(value[0], str) and op not in ('=', '!='):
+            return [], False
+        return [(column, op, value[0])], True
+
+    if 'Function' in node:
+        function = node['Function']
+        inputs = function.get('input')
+        kind = function.get('function')
+        check = {'IsNull': 'is null', 'IsNotNull': 'is not null'}.get(
+            kind.get('Boolean') if isinstance(kind, dict) else None
+        )
+        if check and isinstance(inputs, list) and len(inputs) == 1 and _column(inputs[0]):
+            return [(_column(inputs[0]), check, None)], True
+
+    return [], False
+
+
+def scan_polars(scan, **read_sql_options) -> polars.LazyFrame:
+    """
+    Returns a ``polars.LazyFrame`` of a ``conecta.Scan`` with a polars IO plugin, the columns
+    polars needs, the comparisons of columns with numbers, strings or bools, the null checks
+    and the row limit of the query are pushed down to the database before it is loaded. The
+    predicate is always applied again by polars, so parts of it that cannot be pushed down
+    are still applied.
+
+    ``read_sql_options`` are passed to ``conecta.read_sql``, e.g. ``partition_on`` and
+    ``partition_num``.
+
+    Examples:
+        >>> lf = conecta.scan_sql(conn, 'lineitem').to_polars(partition_on='l_orderkey',
+        ...                                                   partition_num=4)
+        >>> lf.filter(polars.col('l_quantity') > 10).select('l_orderkey').collect()
+    """
+    from polars.io.plugins import register_io_source
+
+    schema = from_arrow(scan.schema().empty_table()).schema
+    partitioned = read_sql_options.get('partition_on') is not None
+
+    def io_source(with_columns: list[str] | None,
+                  predicate: polars.Expr | None,
+                  n_rows: int | None,
+                  batch_size: int | None):
+        pushed = scan
+        filters, complete = [], True
+        if predicate is not None:
+            try:
+                serialized = json.loads(predicate.meta.serialize(format='json'))
+                filters, complete = _scan_filters(serialized)
+            except Exception:
+                filters, complete = [], False
+            for column, op, value in filters:
+                pushed = pushed.filter(column, op, value)
+
+        if with_columns:
+            needed = list(with_columns)
+            if predicate is not None:
+                needed += [c for c in predicate.meta.root_names() if c not in needed]
+            pushed = pushed.select(*needed)
+
+        if n_rows is not None and complete and not partitioned:
+            pushed = pushed.limit(n_rows)
+
+        df = from_arrow(pushed.read_sql(return_backend='pyarrow', **read_sql_options))
+        if predicate is not None:
+            df = df.filter(predicate)
+        if with_columns:
+            df = df.select(with_columns)
+        if n_rows is not None:
+            df = df.head(n_rows)
+
+        if batch_size:
+            yield from df.iter_slices(batch_size)
+        else:
+            yield df
+
+    return register_io_source(io_source, schema=schema)
+
+
 def _patch_polars():
     """Monkey patches polars `_read_sql_connectorx` with conecta's `_read_sql_conecta`"""
     polars.io.database._utils._read_sql_connectorx = _read_sql_conecta
@@ -1,5 +1,7 @@
 import time
 
+import pytest
+
 import conecta
 import pyarrow
 
@@ -29,3 +31,162 @@ def test_postgis_types(postgres_postgis_conn, postgres_postgis_types_query):
             'geom_collection_text': 'GEOMETRYCOLLECTION(POINT(2 0),POLYGON((0 0,1 0,1 1,0 1,0 0)))'
         }
     ]
+
+
+def test_unknown_types_text(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        'select id, interval_, int_range from pg_datatypes',
+        unknown_types='text'
+    )
+    assert table.num_rows == 1
+    assert table.schema.field('interval_').type == pyarrow.string()
+    assert table.schema.field('interval_').metadata[b'conecta.pg_type'] == b'interval'
+    assert table.schema.field('int_range').metadata[b'conecta.pg_type'] == b'int4range'
+
+
+def test_postgis_types_geoarrow(postgres_postgis_conn, postgres_postgis_types_query):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        postgres_postgis_conn,
+        postgres_postgis_types_query,
+        geo_output='geoarrow'
+    )
+    field = table.schema.field('point')
+    assert field.type == pyarrow.binary()
+    assert field.metadata == {
+        b'ARROW:extension:name': b'geoarrow.wkb',
+        b'ARROW:extension:metadata': b'{"crs":"EPSG:4326","crs_type":"authority_code"}'
+    }
+    # ISO WKB, the SRID is in the metadata.
+    assert table['point'].to_pylist() == [
+        b'\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\xc0X@\x00\x00\x00\x00\x00H\x88@'
+    ]
+
+
+def test_geometric_types_geoarrow(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        "select point '(1, 2)' as point_, "
+        "polygon '((0,0),(1,0),(1,1))' as polygon_, "
+        "box '((1,1),(0,0))' as box_, "
+        "path '[(0,0),(1,2)]' as open_path_, "
+        "path '((0,0),(1,2),(3,0))' as closed_path_",
+        geo_output='geoarrow'
+    )
+    assert table.schema.field('point_').metadata[b'ARROW:extension:name'] == b'geoarrow.point'
+    assert table.schema.field('polygon_').metadata[b'ARROW:extension:name'] == b'geoarrow.polygon'
+    assert table.to_pylist() == [
+        {
+            'point_': {'x': 1.0, 'y': 2.0},
+            'polygon_': [[
+                {'x': 0.0, 'y': 0.0},
+                {'x': 1.0, 'y': 0.0},
+                {'x': 1.0, 'y': 1.0},
+                {'x': 0.0, 'y': 0.0},
+            ]],
+            'box_': {'xmin': 0.0, 'ymin': 0.0, 'xmax': 1.0, 'ymax': 1.0},
+            'open_path_': [{'x': 0.0, 'y': 0.0}, {'x': 1.0, 'y': 2.0}],
+            'closed_path_': [
+                {'x': 0.0, 'y': 0.0},
+                {'x': 1.0, 'y': 2.0},
+                {'x': 3.0, 'y': 0.0},
+                {'x': 0.0, 'y': 0.0},
+            ],
+        }
+    ]
+
+
+def test_catalog_metadata(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        'select l_orderkey, l_partkey, l_orderkey + 1 as next_key from lineitem_small limit 10'
+    )
+    orderkey = table.schema.field('l_orderkey')
+    assert not orderkey.nullable
+    assert orderkey.metadata == {
+        b'conecta.pg_type': b'integer',
+        b'conecta.table': b'lineitem_small',
+    }
+    assert table.schema.field('l_partkey').nullable
+
+    # Expressions do not come from a table.
+    next_key = table.schema.field('next_key')
+    assert next_key.nullable
+    assert next_key.metadata == {b'conecta.pg_type': b'int4'}
+
+
+def test_catalog_metadata_outer_join(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        'select b.l_orderkey from (select 1) as a '
+        'left join lineitem_small b on false'
+    )
+    # NOT NULL in the table, but the outer join produces nulls.
+    assert table.schema.field('l_orderkey').nullable
+    assert table['l_orderkey'].null_count == 1
+
+
+def test_schema_overrides(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        'select l_orderkey, l_extendedprice, l_shipmode, l_shipinstruct, l_shipdate::timestamp as ts '
+        'from lineitem_small limit 10',
+        schema_overrides={
+            'l_orderkey': 'int64',
+            'l_extendedprice': 'decimal128(15, 2)',
+            'l_shipmode': 'dictionary',
+            'l_shipinstruct': 'string_view',
+            'ts': 'timestamp[ms]',
+        }
+    )
+    assert table.schema.field('l_orderkey').type == pyarrow.int64()
+    assert table.schema.field('l_extendedprice').type == pyarrow.decimal128(15, 2)
+    assert table.schema.field('l_shipmode').type == pyarrow.dictionary(pyarrow.int32(), pyarrow.string())
+    assert table.schema.field('l_shipinstruct').type == pyarrow.string_view()
+    assert table.schema.field('ts').type == pyarrow.timestamp('ms')
+    assert table.num_rows == 10
+
+
+def test_schema_overrides_invalid(pg_conn):
+    with pytest.raises(ValueError):
+        conecta.read_sql(
+            pg_conn,
+            'select l_orderkey from lineitem_small limit 10',
+            schema_overrides={'l_orderkey': 'not_a_type'}
+        )
+
+
+def test_string_encoding_auto(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        'select l_shipmode, l_comment from lineitem_small',
+        string_encoding='auto',
+    )
+    assert table.schema.field('l_shipmode').type == pyarrow.dictionary(pyarrow.int32(), pyarrow.string())
+    assert table.schema.field('l_comment').type == pyarrow.string()
+    assert set(table.column('l_shipmode').to_pylist()) <= {
+        'AIR', 'FOB', 'MAIL', 'RAIL', 'REG AIR', 'SHIP', 'TRUCK'
+    }
+
+
+def test_string_encoding_auto_overridden(pg_conn):
+    table: pyarrow.lib.Table = conecta.read_sql(
+        pg_conn,
+        'select l_shipmode from lineitem_small',
+        string_encoding='auto',
+        schema_overrides={'l_shipmode': 'string'},
+    )
+    assert table.schema.field('l_shipmode').type == pyarrow.string()
+
+
+def test_preallocation_variable_length(pg_conn):
+    query = "select l_orderkey, l_comment, l_comment || '!' as expr from lineitem_small"
+    table = conecta.read_sql(pg_conn, query, partition_on='l_orderkey', partition_num=2)
+    preallocated = conecta.read_sql(
+        pg_conn,
+        query,
+        partition_on='l_orderkey',
+        partition_num=2,
+        preallocation=True,
+    )
+    assert preallocated.equals(table)
@@ -0,0 +1,41 @@
+import _thread
+import threading
+import time
+
+import pytest
+
+import conecta
+
+SLOW_QUERY = 'select 1 as a from pg_sleep(10)'
+
+
+def test_timeout(pg_conn):
+    start = time.monotonic()
+    with pytest.raises(TimeoutError):
+        conecta.read_sql(pg_conn, SLOW_QUERY, timeout=0.5)
+
+    # The query is cancelled in the database instead of waiting for it.
+    assert time.monotonic() - start < 5
+
+
+def test_timeout_not_reached(pg_conn):
+    table = conecta.read_sql(pg_conn, 'select * from lineitem_small', timeout=60)
+    assert table.num_rows == 10_000
+
+
+def test_timeout_connection_reusable(pg_conn):
+    with conecta.Connection(pg_conn, max_pool_size=1) as conn:
+        with pytest.raises(TimeoutError):
+            conn.read_sql(SLOW_QUERY, timeout=0.5)
+
+        table = conn.read_sql('select * from lineitem_small')
+        assert table.num_rows == 10_000
+
+
+def test_keyboard_interrupt(pg_conn):
+    threading.Timer(0.5, _thread.interrupt_main).start()
+
+    start = time.monotonic()
+    with pytest.raises(KeyboardInterrupt):
+        conecta.read_sql(pg_conn, SLOW_QUERY)
+    assert time.monotonic() - start < 5
@@ -0,0 +1,48 @@
+import pyarrow
+import pytest
+
+import conecta
+
+
+def test_connection_read_sql(pg_conn):
+    with conecta.Connection(pg_conn, max_pool_size=4) as conn:
+        partitioned = conn.read_sql('select * from lineitem_small',
+                                    partition_on='l_orderkey',
+                                    partition_num=4)
+        single = conn.read_sql('select * from lineitem_small')
+
+    assert partitioned.num_rows == single.num_rows == 10_000
+    assert partitioned.schema == single.schema
+    assert partitioned.schema == conecta.read_sql(pg_conn, 'select * from lineitem_small').schema
+
+
+def test_connection_more_partitions_than_connections(pg_conn):
+    with conecta.Connection(pg_conn, max_pool_size=1) as conn:
+        table = conn.read_sql('select * from lineitem_small',
+                              partition_on='l_orderkey',
+                              partition_num=4)
+    assert table.num_rows == 10_000
+
+
+def test_connection_plan(pg_conn):
+    with conecta.Connection(pg_conn) as conn:
+        plan = conn.plan('select * from lineitem_small', partition_on='l_orderkey', partition_num=4)
+    assert len(plan.data_queries) == 4
+
+
+def test_connection_schema(pg_conn):
+    with conecta.Connection(pg_conn) as conn:
+        schema = conn.schema('select l_orderkey, l_comment from lineitem_small',
+                             schema_overrides={'l_orderkey': 'int64'})
+    assert schema.names == ['l_orderkey', 'l_comment']
+    assert schema.field('l_orderkey').type == pyarrow.int64()
+
+
+def test_connection_closed(pg_conn):
+    conn = conecta.Connection(pg_conn, min_idle=0)
+    assert not conn.closed
+    conn.close()
+    assert conn.closed
+
+    with pytest.raises(ValueError, match='closed'):
+        conn.read_sql('select 1')
@@ -0,0 +1,65 @@
+import pyarrow
+import pyarrow.feather
+import pyarrow.ipc
+import pyarrow.parquet
+import pytest
+
+import conecta
+
+
+def test_export_sql_parquet(pg_conn, tmp_path):
+    path = tmp_path / 'lineitem.parquet'
+    conecta.export_sql(
+        pg_conn,
+        'select * from lineitem_small',
+        path=str(path),
+        partition_on='l_orderkey',
+        partition_num=4,
+        compression='zstd(3)',
+        row_group_size=1000,
+        batch_size=500,
+    )
+    metadata = pyarrow.parquet.read_metadata(path)
+    assert metadata.num_rows == 10_000
+    assert metadata.num_columns == 16
+    assert metadata.num_row_groups >= 10
+    assert metadata.row_group(0).column(0).compression == 'ZSTD'
+
+
+def test_export_sql_ipc(pg_conn, tmp_path):
+    path = tmp_path / 'lineitem.arrow'
+    conecta.export_sql(pg_conn, 'select * from lineitem_small', path=str(path), format='ipc')
+
+    table = pyarrow.feather.read_table(path)
+    expected = conecta.read_sql(pg_conn, 'select * from lineitem_small')
+    assert table.num_rows == 10_000
+    assert table.column_names == expected.column_names
+
+
+def test_export_sql_ipc_dictionary(pg_conn, tmp_path):
+    """Every partition has its own dictionary, the IPC file has the strings instead."""
+    path = tmp_path / 'lineitem.arrow'
+    conecta.export_sql(pg_conn, 'select l_orderkey, l_shipmode from lineitem_small',
+                       path=str(path),
+                       format='ipc', partition_on='l_orderkey', partition_num=4,
+                       schema_overrides={'l_shipmode': 'dictionary'})
+
+    table = pyarrow.feather.read_table(path)
+    assert table.num_rows == 10_000
+    assert table.schema.field('l_shipmode').type == pyarrow.string()
+
+
+def test_export_sql_ipc_stream(pg_conn, tmp_path):
+    path = tmp_path / 'lineitem.arrows'
+    conecta.export_sql(
+        pg_conn, 'select * from lineitem_small', path=str(path), format='ipc_stream', batch_size=1000
+    )
+    with pyarrow.ipc.open_stream(path) as reader:
+        batches = list(reader)
+    assert sum(batch.num_rows for batch in batches) == 10_000
+    assert max(batch.num_rows for batch in batches) <= 1000
+
+
+def test_export_sql_invalid_format(pg_conn, tmp_path):
+    with pytest.raises(ValueError):
+        conecta.export_sql(pg_conn, 'select 1', path=str(tmp_path / 'x'), format='csv')
@@ -49,3 +49,27 @@ def test_patch_polars_decorator():
         read_database_uri("", "")
 
         f.assert_called_once()
+
+
+def test_scan_polars(pg_conn):
+    """Tests that the columns and filters of a LazyFrame of a scan are pushed down."""
+    import polars as pl
+    import conecta
+
+    lf = conecta.scan_sql(pg_conn, 'lineitem_small').to_polars(partition_on='l_orderkey',
+                                                               partition_num=4)
+    with mock.patch('conecta.read_sql', wraps=conecta.read_sql) as f:
+        df = (lf.filter((pl.col('l_quantity') > 10)
+                        & (pl.col('l_comment').str.contains('final')))
+              .select('l_orderkey')
+              .collect())
+        query = f.call_args.args[1]
+
+    assert '"l_quantity" > 10' in query
+    assert 'l_partkey' not in query
+    assert df.columns == ['l_orderkey']
+    expected = conecta.read_sql(pg_conn, "select count(*) from lineitem_small "
+                                         "where l_quantity > 10 and l_comment like '%final%'")
+    assert df.height == expected.column(0)[0].as_py()
+
+    assert lf.head(5).collect().height == 5
@@ -30,3 +30,23 @@ def test_partition_plan_preallocation(pg_conn: str):
     assert partition.min_value == 1_108_353
     assert len(partition.data_queries) == 2
     assert partition.partition_config.preallocation == False
+
+
+def test_partition_plan_explain(pg_conn: str):
+    partition = create_partition_plan(
+        pg_conn,
+        ['select * from lineitem_small'],
+        'l_orderkey',
+        partition_num=2,
+        explain=True,
+    )
+
+    assert len(partition.explain) == 2
+    for explain in partition.explain:
+        assert explain.estimated_rows > 0
+        assert explain.total_cost >= explain.startup_cost
+        assert explain.relation_name == 'lineitem_small'
+        assert explain.scan_type in ('Seq Scan', 'Index Scan', 'Bitmap Heap Scan')
+
+    # Not explained by default.
+    assert create_partition_plan(pg_conn, ['select 1']).explain == []
@@ -0,0 +1,68 @@
+import time
+
+import pytest
+
+import conecta
+
+
+def test_read_sql_progress(pg_conn):
+    calls = []
+    table = conecta.read_sql(pg_conn,
+                             'select * from lineitem_small',
+                             partition_on='l_orderkey',
+                             partition_num=4,
+                             preallocation=True,
+                             progress=calls.append)
+
+    # The last call is always made, with the final progress.
+    last = calls[-1]
+    assert last.done
+    assert last.rows == table.num_rows == 10_000
+    assert last.bytes > 0
+    # With preallocation every partition counts its rows.
+    assert last.estimated_rows == 10_000
+    assert last.partitions == last.finished_partitions == 4
+    assert not any(progress.done for progress in calls[:-1])
+
+
+def test_read_sql_progress_slow(pg_conn):
+    calls = []
+    conecta.read_sql(pg_conn, 'select 1 as a from pg_sleep(1.2)', progress=calls.append)
+    # Called every half a second while it loads.
+    assert len(calls) >= 3
+    assert calls[0].rows == 0
+
+
+def test_progress_raises(pg_conn):
+    def progress(_):
+        raise ValueError('stop')
+
+    start = time.monotonic()
+    with pytest.raises(ValueError, match='stop'):
+        conecta.read_sql(pg_conn, 'select 1 as a from pg_sleep(10)', progress=progress)
+
+    # The load is cancelled instead of waiting for it.
+    assert time.monotonic() - start < 5
+
+
+def test_progress_tqdm(pg_conn):
+    tqdm = pytest.importorskip('tqdm')
+    bar = tqdm.tqdm()
+    with conecta.Connection(pg_conn) as conn:
+        conn.read_sql('select * from lineitem_small', progress=bar)
+    assert bar.n == bar.total == 10_000
+
+
+def test_progress_not_valid(pg_conn):
+    with pytest.raises(ValueError):
+        conecta.read_sql(pg_conn, 'select 1 as a', progress=1)
+
+
+def test_export_sql_progress(pg_conn, tmp_path):
+    calls = []
+    conecta.export_sql(pg_conn,
+                       'select * from lineitem_small',
+                       tmp_path / 'lineitem.parquet',
+                       progress=calls.append)
+    assert calls[-1].done
+    assert calls[-1].rows == 10_000
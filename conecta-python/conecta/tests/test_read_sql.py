@@ -82,3 +82,98 @@ def test_read_sql_queries(pg_conn):
     table = conecta.read_sql(pg_conn, query=queries)
     assert table.num_rows == 10_000
     assert table.num_columns == 16
+
+
+def test_read_sql_partitioned_rewrites(pg_conn):
+    # Semicolons, comments and limits are kept within the partitioned query.
+    for query, rows in [
+        ('select * from lineitem_small; -- all of it', 10_000),
+        ('table lineitem_small', 10_000),
+        ('select * from lineitem_small order by l_orderkey limit 100', 100),
+        ('with t as (select * from lineitem_small) select * from t', 10_000),
+    ]:
+        table = conecta.read_sql(pg_conn, query, partition_on='l_orderkey', partition_num=4)
+        assert table.num_rows == rows
+
+
+@pytest.mark.parametrize('query', [
+    'select 1; select 2',
+    'delete from lineitem_small',
+    'with t as (delete from lineitem_small returning *) select * from t',
+])
+def test_read_sql_not_a_query(pg_conn, query):
+    with pytest.raises(BaseException, match='Expected one query|Only queries that read data'):
+        conecta.read_sql(pg_conn, query)
+
+
+def test_read_sql_partition_on_quoted(pg_conn):
+    query = 'select l_orderkey as "Order Id", l_partkey as "select" from lineitem_small'
+    for partition_on in ['"Order Id"', 'select']:
+        table = conecta.read_sql(pg_conn, query, partition_on=partition_on, partition_num=4)
+        assert table.num_rows == 10_000
+
+
+@pytest.mark.parametrize('partition_on', [
+    'l_orderkey % 1000',
+    'hashtext(l_comment)',
+    'extract(epoch from l_shipdate::timestamp)',
+])
+def test_read_sql_partition_on_expression(pg_conn, partition_on):
+    table = conecta.read_sql(pg_conn, 'select * from lineitem_small', partition_on=partition_on,
+                             partition_num=4)
+    assert table.num_rows == 10_000
+    assert table.num_columns == 16
+
+
+@pytest.mark.parametrize('partition_on', [
+    'l_orderkey; drop table lineitem_small',
+    'l_orderkey) from lineitem_small; --',
+    'Order Id',
+    'l_orderkey + (select 1)',
+    'li.l_orderkey % 10',
+])
+def test_read_sql_partition_on_not_a_column(pg_conn, partition_on):
+    with pytest.raises(BaseException, match='partition_on'):
+        conecta.read_sql(pg_conn, 'select * from lineitem_small', partition_on=partition_on,
+                         partition_num=2)
+
+
+def test_read_sql_partition_on_keys(pg_conn):
+    plan = conecta.create_partition_plan(pg_conn, ['select * from lineitem_small'],
+                                         partition_on='l_orderkey, l_linenumber', partition_num=4)
+    assert len(plan.key_bounds) == 4
+    assert plan.min_value is None
+
+    table = conecta.read_sql(pg_conn, 'select * from lineitem_small',
+                             partition_on='l_shipmode, l_orderkey, l_linenumber', partition_num=4,
+                             preallocation=True)
+    assert table.num_rows == 10_000
+    assert table.num_columns == 16
+
+
+def test_read_sql_partition_on_keys_nulls(pg_conn):
+    query = "select a, nullif(a % 10, 0) as b, 'it''s ' || a as c from generate_series(1, 1000) as a"
+    plan = conecta.create_partition_plan(pg_conn, [query], partition_on='b, c', partition_num=4)
+    # The key bounds are parameters, the rows with a null key are in the last partition.
+    assert len(plan.data_queries) == 5
+    assert [len(params) for params in plan.data_params] == [4, 4, 4, 2, 0]
+    assert all('$1' in data_query for data_query in plan.data_queries[:4])
+
+    table = conecta.read_sql(pg_conn, query, partition_on='b, c', partition_num=4)
+    assert table.num_rows == 1000
+    assert table['b'].null_count == 100
+
+
+@pytest.mark.parametrize('query, partition_on, num_rows', [
+    ('select * from lineitem_small where false', 'l_orderkey', 0),
+    ('select l_orderkey, null::bigint as key from lineitem_small', 'key', 10_000),
+])
+def test_read_sql_partition_on_no_min_max(pg_conn, query, partition_on, num_rows):
+    # The source is empty or all its keys are null, there is no min and max to partition on.
+    plan = conecta.create_partition_plan(pg_conn, query, partition_on=partition_on,
+                                         partition_num=4)
+    assert plan.min_value is None and plan.max_value is None
+    assert plan.data_queries == [query]
+
+    table = conecta.read_sql(pg_conn, query, partition_on=partition_on, partition_num=4)
+    assert table.num_rows == num_rows
@@ -0,0 +1,65 @@
+import asyncio
+import time
+
+import pyarrow
+import pytest
+
+import conecta
+
+
+def test_read_sql_async(pg_conn):
+    table = asyncio.run(conecta.read_sql_async(pg_conn,
+                                               'select * from lineitem_small',
+                                               partition_on='l_orderkey',
+                                               partition_num=4,
+                                               batch_size=1000))
+    assert isinstance(table, pyarrow.lib.Table)
+    assert table.num_rows == 10_000
+    assert table.num_columns == 16
+    assert table.schema == conecta.read_sql(pg_conn, 'select * from lineitem_small').schema
+
+
+def test_read_sql_async_concurrent(pg_conn):
+    async def main():
+        start = time.monotonic()
+        tables = await asyncio.gather(
+            conecta.read_sql_async(pg_conn, 'select 1 as a from pg_sleep(1)'),
+            conecta.read_sql_async(pg_conn, 'select 2 as a from pg_sleep(1)'),
+        )
+        return tables, time.monotonic() - start
+
+    tables, elapsed = asyncio.run(main())
+    assert [table['a'].to_pylist() for table in tables] == [[1], [2]]
+    # The loads do not block the event loop, so they run at the same time.
+    assert elapsed < 2
+
+
+def test_read_sql_async_error(pg_conn):
+    with pytest.raises(RuntimeError, match='division by zero'):
+        asyncio.run(conecta.read_sql_async(pg_conn, 'select 1 / (a - 1) as b from generate_series(1, 2) as a'))
+
+
+def test_read_sql_async_timeout(pg_conn):
+    async def main():
+        await asyncio.wait_for(conecta.read_sql_async(pg_conn, 'select 1 as a from pg_sleep(10)'), 0.5)
+
+    with pytest.raises(asyncio.TimeoutError):
+        asyncio.run(main())
+
+
+@pytest.mark.parametrize('option', [
+    {'preallocation': True},
+    {'retries': 2},
+    {'retry_backoff': 1.0},
+    {'timeout': 10},
+    {'progress': print},
+])
+def test_read_sql_async_unsupported_option(pg_conn, option):
+    with pytest.raises(ValueError, match=f'does not support {next(iter(option))}='):
+        asyncio.run(conecta.read_sql_async(pg_conn, 'select 1 as a', **option))
+
+
+def test_read_sql_async_default_options(pg_conn):
+    table = asyncio.run(conecta.read_sql_async(pg_conn, 'select 1 as a', preallocation=False,
+                                               retries=0, timeout=None, progress=None))
+    assert table['a'].to_pylist() == [1]
@@ -0,0 +1,39 @@
+import conecta
+
+
+def test_read_sql_report(pg_conn):
+    table, report = conecta.read_sql(pg_conn,
+                                     'select * from lineitem_small',
+                                     partition_on='l_orderkey',
+                                     partition_num=4,
+                                     return_report=True)
+
+    assert isinstance(report, conecta.LoadReport)
+    assert report.protocol == 'binary'
+    assert report.rows == table.num_rows == 10_000
+    assert report.bytes > 0
+    # Only tracked if built with the 'track-memory' feature.
+    assert report.peak_memory is None or report.peak_memory > 0
+    assert report.total >= report.planning + report.metadata
+
+    assert len(report.partitions) == 4
+    assert sum(p.rows for p in report.partitions) == 10_000
+    for partition in report.partitions:
+        assert partition.attempts == 1
+        if partition.rows:
+            assert partition.executed <= partition.first_row <= partition.finished
+
+
+def test_read_sql_report_empty_partition(pg_conn):
+    _, report = conecta.read_sql(pg_conn,
+                                 'select * from lineitem_small where false',
+                                 return_report=True)
+    assert report.rows == 0
+    assert report.partitions[0].first_row is None
+
+
+def test_connection_read_sql_report(pg_conn):
+    with conecta.Connection(pg_conn) as conn:
+        table = conn.read_sql('select 1 as a')
+        _, report = conn.read_sql('select 1 as a', return_report=True)
+    assert table.num_rows == report.rows == 1
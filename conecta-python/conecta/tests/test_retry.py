@@ -0,0 +1,48 @@
+import threading
+import time
+
+import pytest
+import sqlalchemy
+from sqlalchemy import text
+
+import conecta
+
+SLOW_QUERY = 'select 1 as a from pg_sleep(2)'
+
+
+def terminate_slow_query(pg_conn: str, after: float) -> threading.Timer:
+    """Terminates the backend running ``SLOW_QUERY`` after ``after`` seconds, like a
+    connection reset would."""
+
+    def terminate():
+        engine = sqlalchemy.create_engine(pg_conn.replace('postgresql://', 'postgresql+psycopg2://'))
+        with engine.connect() as conn:
+            conn.execute(text(
+                "select pg_terminate_backend(pid) from pg_stat_activity"
+                " where query like '%pg_sleep(2)%' and pid <> pg_backend_pid()"
+            ))
+
+    timer = threading.Timer(after, terminate)
+    timer.start()
+    return timer
+
+
+def test_retry_transient_error(pg_conn):
+    terminate_slow_query(pg_conn, 0.5)
+    table = conecta.read_sql(pg_conn, SLOW_QUERY, retries=1, retry_backoff=0.1)
+    assert table.column('a').to_pylist() == [1]
+
+
+def test_no_retries(pg_conn):
+    terminate_slow_query(pg_conn, 0.5)
+    with pytest.raises(BaseException, match='terminating connection'):
+        conecta.read_sql(pg_conn, SLOW_QUERY)
+
+
+def test_retry_not_transient(pg_conn):
+    # Errors that would happen again are not retried, it would wait 10 seconds otherwise.
+    start = time.monotonic()
+    with pytest.raises(BaseException, match='division by zero'):
+        conecta.read_sql(pg_conn, 'select 1 / (l_orderkey - l_orderkey) as a from lineitem_small',
+                         retries=3, retry_backoff=10)
+    assert time.monotonic() - start < 5
@@ -0,0 +1,71 @@
+import datetime
+
+import pytest
+
+import conecta
+
+
+def test_scan_sql_query():
+    scan = (conecta.scan_sql('postgres://localhost', 'lineitem_small')
+            .select('l_orderkey', 'l_quantity')
+            .filter('l_quantity', '>', 10)
+            .filter('l_shipmode', 'in', ['AIR', 'MAIL'])
+            .filter('l_shipdate', '<', datetime.date(1995, 1, 1))
+            .filter('l_comment', 'is not null')
+            .limit(5))
+    assert scan.query == (
+        'SELECT "l_orderkey", "l_quantity" FROM lineitem_small WHERE "l_quantity" > $1::BIGINT '
+        'AND "l_shipmode" IN ($2, $3) AND "l_shipdate" < $4 '
+        'AND "l_comment" IS NOT NULL LIMIT 5'
+    )
+    assert scan.params == ['10', 'AIR', 'MAIL', '1995-01-01']
+
+
+def test_scan_sql_is_immutable():
+    scan = conecta.scan_sql('postgres://localhost', 'lineitem_small')
+    scan.select('l_orderkey').filter('l_orderkey', '=', 1)
+    assert scan.query == 'SELECT * FROM lineitem_small'
+
+
+@pytest.mark.parametrize('scan', [
+    lambda s: s.filter('l_orderkey', 'like', '1%'),
+    lambda s: s.filter('l_orderkey', '=', None).query,
+    lambda s: s.filter('l_orderkey', 'in', []).query,
+    lambda s: s.select(),
+])
+def test_scan_sql_invalid_filter(scan):
+    with pytest.raises(ValueError):
+        scan(conecta.scan_sql('postgres://localhost', 'lineitem_small'))
+
+
+def test_scan_sql_not_a_table():
+    with pytest.raises(BaseException, match='has to be the name of a table'):
+        conecta.scan_sql('postgres://localhost', 'lineitem_small; drop table lineitem_small').query
+
+
+def test_scan_sql_read_sql(pg_conn):
+    scan = (conecta.scan_sql(pg_conn, 'lineitem_small')
+            .select('l_quantity', 'l_shipmode')
+            .filter('l_quantity', '>', 10)
+            .filter('l_shipmode', 'in', ['AIR', 'MAIL']))
+    expected = conecta.read_sql(pg_conn, "select count(*) from lineitem_small "
+                                         "where l_quantity > 10 and l_shipmode in ('AIR', 'MAIL')")
+
+    # l_orderkey is not selected, the partitions filter the table on it.
+    for partition_on in ['l_orderkey', 'l_orderkey, l_linenumber']:
+        table = scan.read_sql(partition_on=partition_on, partition_num=4)
+        assert table.num_rows == expected.column(0)[0].as_py()
+        assert table.column_names == ['l_quantity', 'l_shipmode']
+
+    assert scan.limit(7).read_sql().num_rows == 7
+    # The values are parameters, they cannot change the query.
+    assert scan.filter('l_shipmode', '=', "AIR' or 'a' = 'a").read_sql().num_rows == 0
+    with pytest.raises(ValueError, match='cannot be partitioned'):
+        scan.limit(7).read_sql(partition_on='l_orderkey', partition_num=4)
+
+
+def test_scan_sql_connection(pg_conn):
+    with conecta.Connection(pg_conn) as conn:
+        scan = conecta.scan_sql(conn, 'lineitem_small').select('l_orderkey')
+        assert scan.schema().names == ['l_orderkey']
+        assert scan.read_sql(partition_on='l_orderkey', partition_num=2).num_rows == 10_000
@@ -0,0 +1,63 @@
+import datetime
+import decimal
+import uuid
+
+import pyarrow
+import pytest
+
+import conecta
+
+
+def test_write_sql_roundtrip(pg_conn):
+    table = conecta.read_sql(pg_conn, 'select * from lineitem_small')
+
+    rows = conecta.write_sql(pg_conn, 'lineitem_copy', table, max_pool_size=4)
+    assert rows == 10_000
+
+    written = conecta.read_sql(pg_conn, 'select * from lineitem_copy order by l_orderkey, l_linenumber')
+    expected = conecta.read_sql(pg_conn, 'select * from lineitem_small order by l_orderkey, l_linenumber')
+    assert written.column_names == expected.column_names
+    assert written.to_pylist() == expected.to_pylist()
+
+
+def test_write_sql_types(pg_conn):
+    table = pyarrow.table({
+        'b': pyarrow.array([True, None]),
+        'i': pyarrow.array([1, None], pyarrow.int16()),
+        'f': pyarrow.array([1.5, None], pyarrow.float32()),
+        'd': pyarrow.array([decimal.Decimal('-12345.678'), None], pyarrow.decimal128(10, 3)),
+        's': pyarrow.array(['a', None], pyarrow.string_view()),
+        'dict': pyarrow.array(['AIR', None]).dictionary_encode(),
+        'u': pyarrow.array([uuid.UUID(int=1).bytes, None], pyarrow.binary(16)),
+        'date': pyarrow.array([datetime.date(1969, 12, 31), None]),
+        'ts': pyarrow.array([datetime.datetime(2024, 1, 2, 3, 4, 5), None], pyarrow.timestamp('ms')),
+        'arr': pyarrow.array([[1, None, 3], None], pyarrow.list_(pyarrow.int64())),
+    })
+    conecta.write_sql(pg_conn, 'write_types', table)
+
+    written = conecta.read_sql(pg_conn, 'select * from write_types')
+    assert written.column('d').to_pylist() == [decimal.Decimal('-12345.678'), None]
+    assert written.column('s').to_pylist() == ['a', None]
+    assert written.column('dict').to_pylist() == ['AIR', None]
+    assert written.column('date').to_pylist() == [datetime.date(1969, 12, 31), None]
+    assert written.column('ts').to_pylist() == [datetime.datetime(2024, 1, 2, 3, 4, 5), None]
+    assert written.column('arr').to_pylist() == [[1, None, 3], None]
+
+
+def test_write_sql_modes(pg_conn):
+    table = pyarrow.table({'id': pyarrow.array([1, 2], pyarrow.int32())})
+
+    conecta.write_sql(pg_conn, 'write_modes', table)
+    with pytest.raises(BaseException):
+        conecta.write_sql(pg_conn, 'write_modes', table, mode='create')
+
+    conecta.write_sql(pg_conn, 'write_modes', table, mode='append')
+    assert conecta.read_sql(pg_conn, 'select * from write_modes').num_rows == 4
+
+    conecta.write_sql(pg_conn, 'write_modes', table, mode='replace')
+    assert conecta.read_sql(pg_conn, 'select * from write_modes').num_rows == 2
+
+
+def test_write_sql_invalid_mode(pg_conn):
+    with pytest.raises(ValueError):
+        conecta.write_sql(pg_conn, 'write_invalid', pyarrow.table({'id': [1]}), mode='upsert')
@@ -1,35 +1,52 @@
 use log::debug;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use arrow::array::ArrayRef;
+use futures::StreamExt;
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyTypeError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyFloat, PyInt, PyString};
 use pyo3_arrow::error::PyArrowResult;
-use pyo3_arrow::PyTable;
+use pyo3_arrow::{PySchema, PyTable};
 
-use conecta_core::{_create_partition_plan, make_record_batches};
+use conecta_core::cancel::{CancelReason, CancelToken};
+use conecta_core::destination::{
+    Compression, Destination, IpcFileDestination, IpcStreamDestination, ParquetConfig,
+    ParquetDestination,
+};
+use conecta_core::partition::PartitionConfig;
+use conecta_core::retry::RetryConfig;
+use conecta_core::scan::{FilterOp, Scan, ScanFilter, ScanValue};
+use conecta_core::schema::{NativeType, Schema, SchemaConfig};
+use conecta_core::sink::WriteMode;
+use conecta_core::trace::Span;
+use conecta_core::{
+    _create_partition_plan, make_record_batches, span, Connection, LoadConfig, LoadError,
+    LoadReport, PoolConfig, Progress,
+};
+
+mod otlp;
 
 #[pyfunction]
 fn create_partition_plan(
     // Source.
     connection_string: &str,
 
-    // Partition Configuration.
-    query: Vec<String>,
-    partition_on: Option<String>,
-    partition_range: Option<(i64, i64)>,
-    partition_num: Option<u16>,
-
-    // Extra configuration.
-    max_pool_size: Option<u32>,
-    preallocation: bool,
+    // Load configuration.
+    options: LoadOptions,
+    explain: bool,
 ) -> PyResult<String> {
     let plan = _create_partition_plan(
         connection_string,
-        query,
-        partition_on,
-        partition_range,
-        partition_num,
-        max_pool_size,
-        preallocation,
+        options.partition_config(),
+        options.max_pool_size,
+        explain,
     );
 
     let json = serde_json::to_string(&plan).map_err(|e| {
@@ -39,49 +56,160 @@ fn create_partition_plan(
     Ok(json)
 }
 
-#[pyfunction]
-pub fn read_sql(
-    py: Python,
-    // Source
-    connection_string: &str,
+/// How often a load checks for Python signals, e.g. Ctrl-C.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the `progress` callback of a load is called.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Parses a string option given from Python, raising `ValueError` if it is not valid.
+fn parse_option<T: FromStr<Err = String>>(value: &str) -> PyResult<T> {
+    value
+        .parse()
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+}
+
+fn parse_schema_overrides(
+    schema_overrides: &HashMap<String, String>,
+) -> PyResult<HashMap<String, NativeType>> {
+    schema_overrides
+        .iter()
+        .map(|(column, ty)| Ok((column.clone(), parse_option(ty)?)))
+        .collect()
+}
 
-    // Partition Configuration
+/// The options of a load, given from Python as a dict with the keys of `_get_extra_conf` and
+/// the partition configuration.
+#[derive(FromPyObject)]
+#[pyo3(from_item_all)]
+pub struct LoadOptions {
+    // Partition configuration.
+    #[pyo3(default)]
     query: Vec<String>,
+    #[pyo3(default)]
     partition_on: Option<String>,
+    #[pyo3(default)]
     partition_range: Option<(i64, i64)>,
+    #[pyo3(default)]
     partition_num: Option<u16>,
+    params: Vec<String>,
 
-    // Extra configuration
+    // Extra configuration.
     max_pool_size: Option<u32>,
     preallocation: bool,
+    unknown_types: String,
+    geo_output: String,
+    string_encoding: String,
+    schema_overrides: Option<HashMap<String, String>>,
+    retries: u32,
+    retry_backoff: f64,
+    timeout: Option<f64>,
+    progress: Option<Py<PyAny>>,
+}
 
-    // Return configuration
-    return_backend: String,
-) -> PyArrowResult<PyObject> {
-    let _ = env_logger::try_init();
-
-    let (arrays, schema) = py.allow_threads(|| {
-        conecta_core::read_sql(
-            connection_string,
-            query,
-            partition_on,
-            partition_range,
-            partition_num,
-            // Extra configuration
-            max_pool_size,
-            preallocation,
+impl LoadOptions {
+    fn partition_config(&self) -> PartitionConfig {
+        PartitionConfig::new(
+            self.query.clone(),
+            self.partition_on.clone(),
+            self.partition_num,
+            self.partition_range,
+            self.preallocation,
         )
-    });
+        .with_params(self.params.clone())
+    }
 
-    let rbs = make_record_batches(
-        arrays,
-        schema
-            .clone()
-            .columns
-            .into_iter()
-            .map(|col| col.name)
-            .collect(),
-    );
+    /// Parses the schema options, raising `ValueError` if one is not valid.
+    fn schema_config(&self) -> PyResult<SchemaConfig> {
+        Ok(SchemaConfig {
+            unknown_types: parse_option(&self.unknown_types)?,
+            geo_output: parse_option(&self.geo_output)?,
+            string_encoding: parse_option(&self.string_encoding)?,
+            schema_overrides: match &self.schema_overrides {
+                Some(overrides) => parse_schema_overrides(overrides)?,
+                None => HashMap::new(),
+            },
+        })
+    }
+
+    fn retry_config(&self) -> RetryConfig {
+        RetryConfig {
+            retries: self.retries,
+            backoff: Duration::from_secs_f64(self.retry_backoff),
+            ..RetryConfig::default()
+        }
+    }
+
+    fn load_config(&self) -> PyResult<LoadConfig> {
+        Ok(LoadConfig {
+            partition_config: self.partition_config(),
+            schema_config: self.schema_config()?,
+            retry: self.retry_config(),
+        })
+    }
+}
+
+/// The options of the file `export_sql` writes to, given from Python as a dict.
+#[derive(FromPyObject)]
+#[pyo3(from_item_all)]
+pub struct ExportOptions {
+    path: PathBuf,
+    format: String,
+    compression: String,
+    row_group_size: usize,
+    batch_size: usize,
+}
+
+impl ExportOptions {
+    /// Creates the file of the export, raising `ValueError` if `format` or `compression` are
+    /// not valid.
+    fn destination(&self) -> PyResult<Box<dyn Destination>> {
+        Ok(match self.format.as_str() {
+            "parquet" => {
+                let compression: Compression = self.compression.parse().map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "compression={:?} is not valid: {}",
+                        self.compression, e
+                    ))
+                })?;
+                Box::new(ParquetDestination::create(
+                    &self.path,
+                    ParquetConfig {
+                        compression,
+                        max_row_group_size: self.row_group_size,
+                    },
+                ))
+            }
+            "ipc" => Box::new(IpcFileDestination::create(&self.path)),
+            "ipc_stream" => Box::new(IpcStreamDestination::create(&self.path)),
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "format={:?} is not valid, options are: 'parquet', 'ipc', 'ipc_stream'",
+                    self.format
+                )))
+            }
+        })
+    }
+}
+
+/// Returns the loaded `arrays` as a table of the Python Arrow library `return_backend`.
+fn to_backend(
+    py: Python,
+    arrays: Vec<Vec<ArrayRef>>,
+    schema: Schema,
+    return_backend: &str,
+) -> PyArrowResult<Py<PyAny>> {
+    let _span = span!(
+        "conecta.to_arrow",
+        backend = return_backend,
+        rows = arrays
+            .iter()
+            .filter_map(|partition| partition.first())
+            .map(|array| array.len())
+            .sum::<usize>()
+    )
+    .entered();
+    let rbs = make_record_batches(arrays, &schema);
 
     debug!("num_rows, num_columns, buffer_size_bytes");
     for rb in &rbs {
@@ -92,8 +220,20 @@ pub fn read_sql(
             rb.get_array_memory_size()
         );
     }
-    let table = PyTable::try_new(rbs, Arc::new(schema.to_arrow()));
-    match return_backend.as_str() {
+    table_to_backend(
+        py,
+        PyTable::try_new(rbs, Arc::new(schema.to_arrow())),
+        return_backend,
+    )
+}
+
+/// Returns `table` as a table of the Python Arrow library `return_backend`.
+fn table_to_backend(
+    py: Python,
+    table: PyResult<PyTable>,
+    return_backend: &str,
+) -> PyArrowResult<Py<PyAny>> {
+    match return_backend {
         "arro3" => Ok(table?.to_arro3(py)?.into()),
         "nanoarrow" => Ok(table?.to_nanoarrow(py)?.into()),
         // We default to pyarrow, its also default on conecta-python
@@ -101,10 +241,449 @@ pub fn read_sql(
     }
 }
 
+/// Returns the table of `to_backend`, with the `report` as JSON if `return_report`.
+fn to_backend_with_report(
+    py: Python,
+    arrays: Vec<Vec<ArrayRef>>,
+    schema: Schema,
+    return_backend: &str,
+    report: LoadReport,
+    return_report: bool,
+) -> PyArrowResult<Py<PyAny>> {
+    let table = to_backend(py, arrays, schema, return_backend)?;
+    if !return_report {
+        return Ok(table);
+    }
+    let report = serde_json::to_string(&report).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Serialization error: {}", e))
+    })?;
+    Ok((table, report).into_pyobject(py)?.into_any().unbind())
+}
+
+/// Calls the `progress` callback with the progress of a load, as the arguments
+/// `(rows, bytes, estimated_rows, partitions, finished_partitions, done)`.
+fn report_progress(
+    py: Python,
+    callback: &Py<PyAny>,
+    progress: &Progress,
+    done: bool,
+) -> PyResult<()> {
+    let snapshot = progress.snapshot();
+    callback.call1(
+        py,
+        (
+            snapshot.rows,
+            snapshot.bytes,
+            snapshot.estimated_rows,
+            snapshot.partitions,
+            snapshot.finished_partitions,
+            done,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Runs `load` on another thread so the Python thread can react to signals, on
+/// `KeyboardInterrupt` the load is cancelled and the interrupt is raised once it stops. If it
+/// times out after `timeout` seconds `TimeoutError` is raised.
+///
+/// The Python thread calls `progress`, if given, every `PROGRESS_INTERVAL` while the load runs
+/// and once more when it finishes, see `report_progress`. If the callback raises the load is
+/// cancelled and its exception is raised.
+fn run_cancellable<T: Send>(
+    py: Python,
+    timeout: Option<f64>,
+    progress: Option<Py<PyAny>>,
+    load: impl FnOnce(&CancelToken, &Progress) -> T + Send,
+) -> PyResult<T> {
+    let cancel_token = CancelToken::new(timeout.map(Duration::from_secs_f64));
+    let load_progress = match progress {
+        Some(_) => Progress::new(),
+        None => Progress::default(),
+    };
+    // The spans of the load are children of the current one, e.g. `conecta.read_sql`.
+    let parent = Span::current();
+
+    let (result, interrupt) = std::thread::scope(|scope| {
+        let (sender, mut receiver) = mpsc::channel();
+        let cancel = &cancel_token;
+        let watched = &load_progress;
+        scope.spawn(move || {
+            let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(|| {
+                parent.in_scope(|| load(cancel, watched))
+            })));
+        });
+
+        let mut interrupt: Option<PyErr> = None;
+        let mut last_progress = Instant::now();
+        loop {
+            // The receiver is moved in and out as it cannot be shared with the detached thread.
+            let received;
+            (received, receiver) =
+                py.detach(move || (receiver.recv_timeout(SIGNAL_CHECK_INTERVAL), receiver));
+            match received {
+                Ok(result) => return (result, interrupt),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => unreachable!("The load always sends"),
+            }
+            if interrupt.is_none() {
+                if let Err(e) = py.check_signals() {
+                    cancel_token.cancel();
+                    interrupt = Some(e);
+                }
+            }
+            if let Some(callback) = progress.as_ref().filter(|_| interrupt.is_none()) {
+                if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                    last_progress = Instant::now();
+                    if let Err(e) = report_progress(py, callback, &load_progress, false) {
+                        cancel_token.cancel();
+                        interrupt = Some(e);
+                    }
+                }
+            }
+        }
+    });
+
+    if let Some(interrupt) = interrupt {
+        return Err(interrupt);
+    }
+    if let (Some(callback), Ok(_)) = (&progress, &result) {
+        report_progress(py, callback, &load_progress, true)?;
+    }
+    result.or_else(|payload| match cancel_token.reason() {
+        Some(CancelReason::TimedOut) => Err(PyTimeoutError::new_err(format!(
+            "The load timed out after {} seconds",
+            timeout.unwrap()
+        ))),
+        // Other panics are raised as they would have been without the thread.
+        _ => panic::resume_unwind(payload),
+    })
+}
+
+#[pyfunction]
+pub fn read_sql(
+    py: Python,
+    // Source
+    connection_string: &str,
+
+    // Load configuration
+    options: LoadOptions,
+
+    // Return configuration
+    return_backend: String,
+    return_report: bool,
+) -> PyArrowResult<Py<PyAny>> {
+    let _ = env_logger::try_init();
+    let _span = span!("conecta.read_sql").entered();
+
+    let load_config = options.load_config()?;
+    let max_pool_size = options.max_pool_size;
+
+    let (arrays, schema, report) = run_cancellable(
+        py,
+        options.timeout,
+        options.progress,
+        |cancel_token, progress| {
+            conecta_core::read_sql(
+                connection_string,
+                load_config,
+                max_pool_size,
+                cancel_token,
+                progress,
+            )
+        },
+    )?;
+
+    to_backend_with_report(py, arrays, schema, &return_backend, report, return_report)
+}
+
+/// Loads `query` like `read_sql` on tokio-postgres, returns an awaitable of the table that does
+/// not block the event loop, see `conecta_core::read_sql_async`.
+#[pyfunction]
+pub fn read_sql_async<'py>(
+    py: Python<'py>,
+    // Source
+    connection_string: String,
+
+    // Load configuration
+    options: LoadOptions,
+    batch_size: usize,
+
+    // Return configuration
+    return_backend: String,
+) -> PyResult<Bound<'py, PyAny>> {
+    let _ = env_logger::try_init();
+
+    let partition_config = options.partition_config();
+    let schema_config = options.schema_config()?;
+    let max_pool_size = options.max_pool_size;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let to_py_err = |e: LoadError| PyRuntimeError::new_err(e.0);
+        let mut stream = conecta_core::read_sql_async(
+            &connection_string,
+            partition_config,
+            schema_config,
+            max_pool_size,
+            batch_size,
+        )
+        .await
+        .map_err(to_py_err)?;
+
+        let mut batches = vec![];
+        while let Some(batch) = stream.next().await {
+            batches.push(batch.map_err(to_py_err)?);
+        }
+        let table = PyTable::try_new(batches, stream.schema());
+        Ok(Python::attach(|py| {
+            table_to_backend(py, table, &return_backend)
+        })?)
+    })
+}
+
+#[pyfunction]
+pub fn export_sql(
+    py: Python,
+    // Source
+    connection_string: &str,
+
+    // Load configuration
+    options: LoadOptions,
+
+    // Destination configuration
+    export: ExportOptions,
+) -> PyResult<()> {
+    let _ = env_logger::try_init();
+    let _span = span!("conecta.export_sql").entered();
+
+    let load_config = options.load_config()?;
+    let max_pool_size = options.max_pool_size;
+    let destination = export.destination()?;
+
+    run_cancellable(
+        py,
+        options.timeout,
+        options.progress,
+        |cancel_token, progress| {
+            conecta_core::export_sql(
+                connection_string,
+                load_config,
+                max_pool_size,
+                destination.as_ref(),
+                export.batch_size,
+                cancel_token,
+                progress,
+            )
+        },
+    )?;
+    Ok(())
+}
+
+#[pyfunction]
+pub fn write_sql(
+    py: Python,
+    connection_string: &str,
+    table: &str,
+    data: PyTable,
+    mode: String,
+    max_pool_size: Option<u32>,
+) -> PyResult<u64> {
+    let _ = env_logger::try_init();
+
+    let mode: WriteMode = parse_option(&mode)?;
+    let (batches, _) = data.into_inner();
+
+    Ok(py
+        .detach(|| conecta_core::write_sql(connection_string, table, batches, mode, max_pool_size)))
+}
+
+/// Returns the `Scan` of the arguments of `scan_query`, every filter is a tuple of its column,
+/// operator and values.
+fn to_scan(
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<(String, String, Vec<Bound<PyAny>>)>,
+    limit: Option<u64>,
+) -> PyResult<Scan> {
+    let filters = filters
+        .into_iter()
+        .map(|(column, op, values)| {
+            let op: FilterOp = parse_option(&op)?;
+            let values = values.iter().map(to_scan_value).collect::<PyResult<_>>()?;
+            ScanFilter::try_new(column, op, values)
+                .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+        })
+        .collect::<PyResult<_>>()?;
+    Ok(Scan {
+        table,
+        columns,
+        filters,
+        limit,
+    })
+}
+
+/// Converts the value of a filter, other types than `None`, `bool`, `int`, `float` and `str`
+/// have to be given as text, e.g. dates.
+fn to_scan_value(value: &Bound<PyAny>) -> PyResult<ScanValue> {
+    if value.is_none() {
+        Ok(ScanValue::Null)
+    } else if value.is_instance_of::<PyBool>() {
+        Ok(ScanValue::Bool(value.extract()?))
+    } else if value.is_instance_of::<PyInt>() {
+        Ok(ScanValue::Int(value.extract()?))
+    } else if value.is_instance_of::<PyFloat>() {
+        Ok(ScanValue::Float(value.extract()?))
+    } else if value.is_instance_of::<PyString>() {
+        Ok(ScanValue::Text(value.extract()?))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "The filter value {value} of type {} is not supported, give it as text",
+            value.get_type().name()?
+        )))
+    }
+}
+
+/// Returns the SQL query of a scan of `table` for the database of `connection_string` with the
+/// values of its parameters, see `to_scan`.
+#[pyfunction]
+pub fn scan_query(
+    connection_string: &str,
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<(String, String, Vec<Bound<PyAny>>)>,
+    limit: Option<u64>,
+) -> PyResult<(String, Vec<String>)> {
+    let scan = to_scan(table, columns, filters, limit)?;
+    Ok(conecta_core::scan_query(connection_string, &scan))
+}
+
+/// A connection that keeps its pool of connections open across loads, see
+/// `conecta_core::Connection`.
+#[pyclass(name = "Connection")]
+struct PyConnection {
+    /// `None` once closed. Loads running when it is closed keep their own reference, the pool
+    /// is closed when they finish.
+    connection: Option<Arc<Connection>>,
+}
+
+impl PyConnection {
+    fn connection(&self) -> PyResult<Arc<Connection>> {
+        self.connection.clone().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>("The connection is closed")
+        })
+    }
+}
+
+#[pymethods]
+impl PyConnection {
+    #[new]
+    fn new(
+        py: Python,
+        connection_string: &str,
+        max_pool_size: Option<u32>,
+        min_idle: Option<u32>,
+        max_lifetime: Option<f64>,
+        connection_timeout: f64,
+    ) -> PyResult<Self> {
+        let _ = env_logger::try_init();
+
+        let pool_config = PoolConfig {
+            max_size: max_pool_size.unwrap_or(PoolConfig::default().max_size),
+            min_idle,
+            max_lifetime: max_lifetime.map(Duration::from_secs_f64),
+            connection_timeout: Duration::from_secs_f64(connection_timeout),
+        };
+        let connection = py.detach(|| Connection::new(connection_string, pool_config));
+        Ok(PyConnection {
+            connection: Some(Arc::new(connection)),
+        })
+    }
+
+    fn read_sql(
+        &self,
+        py: Python,
+        // Load configuration
+        options: LoadOptions,
+
+        // Return configuration
+        return_backend: String,
+        return_report: bool,
+    ) -> PyArrowResult<Py<PyAny>> {
+        let connection = self.connection()?;
+        let _span = span!("conecta.read_sql").entered();
+        let load_config = options.load_config()?;
+
+        let (arrays, schema, report) = run_cancellable(
+            py,
+            options.timeout,
+            options.progress,
+            |cancel_token, progress| connection.read_sql(load_config, cancel_token, progress),
+        )?;
+        to_backend_with_report(py, arrays, schema, &return_backend, report, return_report)
+    }
+
+    fn plan(&self, py: Python, options: LoadOptions, explain: bool) -> PyResult<String> {
+        let connection = self.connection()?;
+        let partition_config = options.partition_config();
+        let plan = py.detach(|| connection.plan(partition_config, explain));
+
+        serde_json::to_string(&plan).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Serialization error: {}", e))
+        })
+    }
+
+    fn schema(
+        &self,
+        py: Python,
+        query: &str,
+        options: LoadOptions,
+        return_backend: String,
+    ) -> PyResult<Py<PyAny>> {
+        let connection = self.connection()?;
+        let schema_config = options.schema_config()?;
+
+        let schema = py.detach(|| connection.schema(query, &options.params, &schema_config));
+        let schema = PySchema::new(Arc::new(schema.to_arrow()));
+        match return_backend.as_str() {
+            "arro3" => Ok(schema.into_arro3(py)?.into()),
+            "nanoarrow" => Ok(schema.to_nanoarrow(py)?.into()),
+            _ => Ok(schema.into_pyarrow(py)?.into()),
+        }
+    }
+
+    fn scan_query(
+        &self,
+        table: String,
+        columns: Vec<String>,
+        filters: Vec<(String, String, Vec<Bound<PyAny>>)>,
+        limit: Option<u64>,
+    ) -> PyResult<(String, Vec<String>)> {
+        let scan = to_scan(table, columns, filters, limit)?;
+        Ok(self.connection()?.scan_query(&scan))
+    }
+
+    /// Closes the pool of connections, idle connections are closed right away.
+    fn close(&mut self) {
+        self.connection = None;
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.connection.is_none()
+    }
+}
+
 /// A Python module implemented in Rust.
 #[pymodule]
 fn conecta(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(create_partition_plan, m)?)?;
     m.add_function(wrap_pyfunction!(read_sql, m)?)?;
+    m.add_function(wrap_pyfunction!(read_sql_async, m)?)?;
+    m.add_function(wrap_pyfunction!(export_sql, m)?)?;
+    m.add_function(wrap_pyfunction!(write_sql, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_query, m)?)?;
+    m.add_function(wrap_pyfunction!(otlp::enable_tracing, m)?)?;
+    m.add_function(wrap_pyfunction!(otlp::shutdown_tracing, m)?)?;
+    m.add_class::<PyConnection>()?;
     Ok(())
 }
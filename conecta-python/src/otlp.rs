@@ -0,0 +1,89 @@
+//! Exports the `tracing` spans of the loads to an OpenTelemetry collector, behind the `otlp`
+//! feature. See `conecta_core::trace` for the spans.
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+#[cfg(feature = "otlp")]
+mod exporter {
+    use std::sync::Mutex;
+
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// The provider of the installed exporter, kept to flush its spans on shutdown.
+    static PROVIDER: Mutex<Option<SdkTracerProvider>> = Mutex::new(None);
+
+    pub fn enable(endpoint: Option<String>, service_name: String) -> Result<(), String> {
+        let mut provider = PROVIDER.lock().unwrap();
+        if provider.is_some() {
+            return Err("Tracing is already enabled".to_string());
+        }
+
+        // Without an endpoint it is read from OTEL_EXPORTER_OTLP_ENDPOINT.
+        let mut exporter = SpanExporter::builder().with_http();
+        if let Some(endpoint) = endpoint {
+            exporter = exporter.with_endpoint(endpoint);
+        }
+        let exporter = exporter
+            .build()
+            .map_err(|e| format!("Could not create the OTLP exporter: {}", e))?;
+
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(service_name).build())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("conecta")));
+        tracing::subscriber::set_global_default(subscriber)
+            .map_err(|e| format!("Could not install the tracing subscriber: {}", e))?;
+
+        *provider = Some(tracer_provider);
+        Ok(())
+    }
+
+    pub fn shutdown() -> Result<(), String> {
+        match PROVIDER.lock().unwrap().take() {
+            Some(provider) => provider
+                .shutdown()
+                .map_err(|e| format!("Could not export the remaining spans: {}", e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Exports the spans of the loads to the OTLP/HTTP `endpoint`, e.g.
+/// 'http://localhost:4318/v1/traces'.
+#[pyfunction]
+pub fn enable_tracing(py: Python, endpoint: Option<String>, service_name: String) -> PyResult<()> {
+    #[cfg(feature = "otlp")]
+    return py
+        .allow_threads(|| exporter::enable(endpoint, service_name))
+        .map_err(PyRuntimeError::new_err);
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        let _ = (py, endpoint, service_name);
+        Err(PyRuntimeError::new_err(
+            "conecta was built without tracing, build it with the 'otlp' feature",
+        ))
+    }
+}
+
+/// Exports the spans that were not exported yet and stops exporting them.
+#[pyfunction]
+pub fn shutdown_tracing(py: Python) -> PyResult<()> {
+    #[cfg(feature = "otlp")]
+    return py
+        .allow_threads(exporter::shutdown)
+        .map_err(PyRuntimeError::new_err);
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        let _ = py;
+        Ok(())
+    }
+}